@@ -0,0 +1,824 @@
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use sokoban_rs::{
+    config::Config,
+    game::{
+        self, GameState,
+        collection::{Level, LevelCollection},
+        level::{LevelFormat, Strictness},
+        solver::Solver,
+        verify,
+    },
+    progress::Progress,
+    rendering::{cli::CliFrontEnd, theme::Theme},
+    session::Session,
+};
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+/// The extensions of level files, in the order `verify` looks for them.
+const LEVEL_EXTENSIONS: [&str; 3] = ["txt", "xsb", "sok"];
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Sokoban in the terminal")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Play a level in the terminal.
+    Play {
+        #[command(flatten)]
+        source: LevelSource,
+        /// Let the solver play instead of the keyboard.
+        #[arg(long)]
+        autoplay: bool,
+        /// Milliseconds between two autoplay steps.
+        #[arg(long, default_value_t = 100)]
+        speed: u64,
+        /// Load a solution in LURD notation, to be stepped through with redo.
+        #[arg(long, conflicts_with = "autoplay")]
+        solution: Option<PathBuf>,
+        #[command(flatten)]
+        options: PlayOptions,
+    },
+    /// Continue the game saved when the player last quit.
+    Continue {
+        #[command(flatten)]
+        options: PlayOptions,
+    },
+    /// Solve levels and print the solutions in LURD notation.
+    Solve {
+        #[command(flatten)]
+        source: LevelSource,
+        #[command(flatten)]
+        solver: SolverArgs,
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Check every level file in a directory.
+    Validate {
+        dir: PathBuf,
+        /// Treat header mismatches in classic files as errors.
+        #[arg(long)]
+        strict: bool,
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Convert a level file to another format.
+    Convert {
+        input: PathBuf,
+        /// Input format, detected from the file when left out.
+        #[arg(long)]
+        from: Option<LevelFormat>,
+        /// Output format: `classic` or `xsb`.
+        #[arg(long)]
+        to: LevelFormat,
+        /// Only convert the level with this 1-based number.
+        #[arg(short, long)]
+        level: Option<usize>,
+        /// Write to this file instead of standard output.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Generate random levels that the solver can solve.
+    Generate {
+        #[arg(long, default_value_t = 7)]
+        rows: i32,
+        #[arg(long, default_value_t = 7)]
+        cols: i32,
+        /// How many levels to generate.
+        #[arg(short = 'n', long, default_value_t = 1)]
+        count: usize,
+        /// Random levels to try per generated level before giving up.
+        #[arg(long, default_value_t = 1000)]
+        attempts: usize,
+        #[arg(long, default_value = "xsb")]
+        to: LevelFormat,
+    },
+    /// Time the solver on every level of the given files.
+    Bench {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        #[command(flatten)]
+        solver: SolverArgs,
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Check solution files against the levels they belong to.
+    ///
+    /// `mission1.lurd` is checked against `mission1.txt`, `mission1.xsb` or
+    /// `mission1.sok` in the levels directory, the first that exists.
+    Verify {
+        #[arg(required = true)]
+        solutions: Vec<PathBuf>,
+        #[arg(long, default_value = "levels")]
+        levels: PathBuf,
+        /// 1-based number of the level within each collection. Needed when
+        /// a level file holds more than one level.
+        #[arg(short, long)]
+        level: Option<usize>,
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct LevelSource {
+    /// A level file or collection, or a directory of them.
+    file: PathBuf,
+    /// 1-based number of the level within the collection.
+    #[arg(short, long)]
+    level: Option<usize>,
+    /// Treat header mismatches in classic files as errors.
+    #[arg(long)]
+    strict: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct PlayOptions {
+    /// Read settings from this file instead of the user config directory.
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// A built-in theme (classic, unicode, emoji, high-contrast,
+    /// colorblind) or a theme file, overriding the config.
+    #[arg(long)]
+    theme: Option<String>,
+    /// Keep records in this file instead of the user data directory.
+    #[arg(long)]
+    progress: Option<PathBuf>,
+    /// Save an unfinished game to this file instead of the user data
+    /// directory.
+    #[arg(long)]
+    session: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct SolverArgs {
+    /// Give up after expanding this many states.
+    #[arg(long, default_value_t = 1_000_000)]
+    node_limit: usize,
+    /// Heuristic weight. Anything above 1 is faster but no longer
+    /// push-optimal.
+    #[arg(long, default_value_t = 1)]
+    weight: u32,
+}
+
+impl SolverArgs {
+    fn solver(&self) -> Solver {
+        Solver::new()
+            .with_node_limit(self.node_limit)
+            .with_weight(self.weight)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+/// Runs the command and returns whether everything it checked was fine.
+pub fn run(cli: Cli) -> CliResult<bool> {
+    match cli.command {
+        Command::Play {
+            source,
+            autoplay,
+            speed,
+            solution,
+            options,
+        } => play(
+            &source,
+            autoplay,
+            speed,
+            solution.as_deref(),
+            &options,
+            None,
+        ),
+        Command::Continue { options } => continue_game(&options),
+        Command::Solve {
+            source,
+            solver,
+            format,
+        } => solve(&source, &solver, format),
+        Command::Validate {
+            dir,
+            strict,
+            format,
+        } => validate(&dir, strictness(strict), format),
+        Command::Convert {
+            input,
+            from,
+            to,
+            level,
+            output,
+        } => convert(&input, from, to, level, output.as_deref()),
+        Command::Generate {
+            rows,
+            cols,
+            count,
+            attempts,
+            to,
+        } => generate(rows, cols, count, attempts, to),
+        Command::Bench {
+            files,
+            solver,
+            format,
+        } => bench(&files, &solver, format),
+        Command::Verify {
+            solutions,
+            levels,
+            level,
+            format,
+        } => verify_solutions(&solutions, &levels, level, format),
+    }
+}
+
+fn strictness(strict: bool) -> Strictness {
+    if strict {
+        Strictness::Strict
+    } else {
+        Strictness::Lenient
+    }
+}
+
+/// Loads a collection and prints the problems that were tolerated. A
+/// directory is loaded as one collection of every level file in it, leaving
+/// out the files that fail to load.
+fn load(path: &Path, strictness: Strictness) -> CliResult<LevelCollection> {
+    let mut warnings = Vec::new();
+    let levels = load_with(path, strictness, &mut warnings);
+    print_warnings(&warnings);
+    levels
+}
+
+/// Loads a collection like `load`, but adds the problems that were
+/// tolerated to `warnings` instead of printing them.
+fn load_with(
+    path: &Path,
+    strictness: Strictness,
+    warnings: &mut Vec<String>,
+) -> CliResult<LevelCollection> {
+    if !path.is_dir() {
+        return load_file_with(path, strictness, warnings);
+    }
+
+    let mut collections = Vec::new();
+    for file in level_files(path)? {
+        match load_file_with(&file, strictness, warnings) {
+            Ok(levels) => collections.push(levels),
+            Err(err) => warnings.push(format!("warning: skipping {err}")),
+        }
+    }
+    let title = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned());
+    Ok(LevelCollection::concat(title, collections)
+        .map_err(|err| format!("{}: {err}", path.display()))?)
+}
+
+fn load_file(path: &Path, strictness: Strictness) -> CliResult<LevelCollection> {
+    let mut warnings = Vec::new();
+    let levels = load_file_with(path, strictness, &mut warnings);
+    print_warnings(&warnings);
+    levels
+}
+
+fn load_file_with(
+    path: &Path,
+    strictness: Strictness,
+    warnings: &mut Vec<String>,
+) -> CliResult<LevelCollection> {
+    let (levels, found) = LevelCollection::from_file_with(path, strictness)
+        .map_err(|err| format!("{}: {err}", path.display()))?;
+    for warning in found {
+        warnings.push(format!("{}: warning: {warning}", path.display()));
+    }
+    Ok(levels)
+}
+
+fn print_warnings(warnings: &[String]) {
+    for warning in warnings {
+        eprintln!("{warning}");
+    }
+}
+
+/// The level files in a directory, by name.
+fn level_files(dir: &Path) -> CliResult<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(|err| format!("{}: {err}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| LEVEL_EXTENSIONS.contains(&ext))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// The level with the given number, or every level when there is none.
+fn select(levels: &LevelCollection, number: Option<usize>) -> CliResult<Vec<&Level>> {
+    match number {
+        Some(number) => {
+            let level = levels.get(number).ok_or_else(|| {
+                format!("there is no level {number}, the file has {}", levels.len())
+            })?;
+            Ok(vec![level])
+        }
+        None => Ok(levels.iter().collect()),
+    }
+}
+
+fn print_json(value: &impl Serialize) -> CliResult<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Plays the game saved by the last `play` or `continue` that was quit
+/// before the level was solved.
+fn continue_game(options: &PlayOptions) -> CliResult<bool> {
+    let Some(path) = options.session.clone().or_else(Session::default_path) else {
+        return Err("no data directory to find a saved game in".into());
+    };
+    let session = Session::from_file(&path)
+        .map_err(|err| format!("{}: {err}", path.display()))?
+        .ok_or("no saved game to continue")?;
+    let source = LevelSource {
+        file: session.source.clone(),
+        level: Some(session.level),
+        strict: false,
+    };
+    play(&source, false, 100, None, options, Some(session))
+}
+
+fn play(
+    source: &LevelSource,
+    autoplay: bool,
+    speed: u64,
+    solution: Option<&Path>,
+    options: &PlayOptions,
+    resume: Option<Session>,
+) -> CliResult<bool> {
+    let config_path = options.config.as_deref();
+    let config = match config_path {
+        Some(path) => Config::from_file(path),
+        None => Config::load(),
+    }
+    .map_err(
+        |err| match config_path.map(PathBuf::from).or_else(Config::default_path) {
+            Some(path) => format!("{}: {err}", path.display()),
+            None => err.to_string(),
+        },
+    )?;
+    let theme = match options.theme.as_deref() {
+        Some(theme) => Theme::load(theme).map_err(|err| format!("{theme}: {err}"))?,
+        None => config.theme,
+    };
+    let lurd = solution
+        .map(|path| {
+            std::fs::read_to_string(path).map_err(|err| format!("{}: {err}", path.display()))
+        })
+        .transpose()?;
+    // Warnings are held back until the game has given the terminal back,
+    // as it clears the screen when it starts
+    let mut warnings = Vec::new();
+    let levels = load_with(&source.file, strictness(source.strict), &mut warnings)
+        .and_then(|levels| {
+            // A saved level is looked up by content, so its number may be
+            // stale
+            if let Some(number) = source.level
+                && resume.is_none()
+            {
+                select(&levels, Some(number))?;
+            }
+            Ok(levels)
+        })
+        .inspect_err(|_| print_warnings(&warnings))?;
+    let progress = match &options.progress {
+        Some(path) => Progress::from_file(path),
+        None => Progress::load(),
+    }
+    .unwrap_or_else(|err| {
+        // Keep the records in memory rather than overwrite a file we could
+        // not read
+        warnings.push(format!("warning: {err}, records will not be saved"));
+        Progress::default()
+    });
+    let mode = if autoplay {
+        game::Mode::Autoplay
+    } else {
+        game::Mode::Interactive
+    };
+
+    // Without a level to start with, let the player pick one
+    let start_in_menu = source.level.is_none() && levels.len() > 1 && !autoplay && lurd.is_none();
+    let mut game = game::Game::<CliFrontEnd>::with_levels(levels, source.level.unwrap_or(1), mode);
+    game.start_in_menu = start_in_menu;
+    game.autoplay_delay = Duration::from_millis(speed);
+    game.front_end.keymap = config.keymap;
+    game.front_end.set_theme(theme);
+    game.progress = progress;
+    if let Some(session) = &resume {
+        game.resume(session)
+            .map_err(|err| format!("{}: {err}", session.source.display()))?;
+    }
+    if let (Some(lurd), Some(path)) = (&lurd, solution) {
+        game.load_solution(lurd)
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+    }
+    game.run();
+
+    let solution = game.state.is_solved().then(|| game.history().to_lurd());
+    let saved = game.progress.save();
+    // Keep an unfinished game for `continue`, and forget it once there is
+    // nothing left to continue
+    let history = game.history();
+    let unfinished =
+        solution.is_none() && !(history.moves().is_empty() && history.redo_moves().is_empty());
+    let session_path = options.session.clone().or_else(Session::default_path);
+    let session_saved = match &session_path {
+        // A replayed solution is not the player's own game
+        _ if autoplay || lurd.is_some() => Ok(false),
+        Some(path) if unfinished => {
+            let source = std::fs::canonicalize(&source.file).unwrap_or(source.file.clone());
+            game.to_session(source).save(path).map(|()| true)
+        }
+        Some(path) => Session::remove(path).map(|()| false),
+        None => Ok(false),
+    };
+    drop(game); // Restore the terminal before printing
+
+    print_warnings(&warnings);
+    if let Err(err) = saved {
+        eprintln!("warning: {err}");
+    }
+    match session_saved {
+        Ok(true) => println!("Game saved, run `sokoban-rs continue` to resume it."),
+        Ok(false) => {}
+        Err(err) => eprintln!("warning: {err}"),
+    }
+    if let Some(solution) = solution {
+        println!("Solution: {solution}");
+    }
+    Ok(true)
+}
+
+#[derive(Serialize)]
+struct SolveReport {
+    level: usize,
+    name: String,
+    solved: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lurd: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    moves: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pushes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    push_optimal: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    explored: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    seconds: f64,
+}
+
+impl SolveReport {
+    fn new(level: &Level, solver: &Solver) -> Self {
+        let start = Instant::now();
+        let result = solver.solve(&level.state);
+        let seconds = start.elapsed().as_secs_f64();
+
+        let mut report = SolveReport {
+            level: level.number,
+            name: level.name(),
+            solved: false,
+            lurd: None,
+            moves: None,
+            pushes: None,
+            push_optimal: None,
+            explored: None,
+            error: None,
+            seconds,
+        };
+        match result {
+            Ok(solution) => {
+                report.solved = true;
+                report.moves = Some(solution.steps.len());
+                report.pushes = Some(solution.pushes.len());
+                report.push_optimal = Some(solution.push_optimal);
+                report.explored = Some(solution.explored);
+                report.lurd = Some(solution.to_lurd(&level.state));
+            }
+            Err(err) => report.error = Some(err.to_string()),
+        }
+        report
+    }
+
+    fn summary(&self) -> String {
+        match &self.error {
+            Some(error) => error.clone(),
+            None => format!(
+                "{} moves, {} pushes{}, {} states in {:.3}s",
+                self.moves.unwrap_or(0),
+                self.pushes.unwrap_or(0),
+                if self.push_optimal == Some(true) {
+                    " (push-optimal)"
+                } else {
+                    ""
+                },
+                self.explored.unwrap_or(0),
+                self.seconds
+            ),
+        }
+    }
+}
+
+fn solve(source: &LevelSource, args: &SolverArgs, format: OutputFormat) -> CliResult<bool> {
+    let levels = load(&source.file, strictness(source.strict))?;
+    let solver = args.solver();
+    let reports: Vec<SolveReport> = select(&levels, source.level)?
+        .into_iter()
+        .map(|level| SolveReport::new(level, &solver))
+        .collect();
+
+    match format {
+        OutputFormat::Human => {
+            for report in &reports {
+                println!("{}: {}", report.name, report.summary());
+                if let Some(lurd) = &report.lurd {
+                    println!("{lurd}");
+                }
+            }
+        }
+        OutputFormat::Json => print_json(&reports)?,
+    }
+
+    Ok(reports.iter().all(|report| report.solved))
+}
+
+#[derive(Serialize)]
+struct FileReport {
+    file: PathBuf,
+    levels: usize,
+    warnings: Vec<String>,
+    errors: Vec<String>,
+}
+
+fn validate(dir: &Path, strictness: Strictness, format: OutputFormat) -> CliResult<bool> {
+    let files = level_files(dir)?;
+
+    let reports: Vec<FileReport> = files
+        .into_iter()
+        .map(|file| {
+            let mut report = FileReport {
+                levels: 0,
+                warnings: Vec::new(),
+                errors: Vec::new(),
+                file,
+            };
+            match LevelCollection::from_file_with(&report.file, strictness) {
+                Ok((levels, warnings)) => {
+                    report.levels = levels.len();
+                    report.warnings = warnings.iter().map(ToString::to_string).collect();
+                    for level in &levels {
+                        for problem in level.state.validate() {
+                            report
+                                .errors
+                                .push(format!("level {}: {problem}", level.number));
+                        }
+                    }
+                }
+                Err(err) => report.errors.push(err.to_string()),
+            }
+            report
+        })
+        .collect();
+
+    match format {
+        OutputFormat::Human => {
+            for report in &reports {
+                let file = report.file.display();
+                for warning in &report.warnings {
+                    println!("{file}: warning: {warning}");
+                }
+                for error in &report.errors {
+                    println!("{file}: error: {error}");
+                }
+                if report.errors.is_empty() {
+                    println!("{file}: ok, {} levels", report.levels);
+                }
+            }
+        }
+        OutputFormat::Json => print_json(&reports)?,
+    }
+
+    Ok(reports.iter().all(|report| report.errors.is_empty()))
+}
+
+fn convert(
+    input: &Path,
+    from: Option<LevelFormat>,
+    to: LevelFormat,
+    number: Option<usize>,
+    output: Option<&Path>,
+) -> CliResult<bool> {
+    let content =
+        std::fs::read_to_string(input).map_err(|err| format!("{}: {err}", input.display()))?;
+    let from = from
+        .or_else(|| LevelFormat::from_path(input))
+        .unwrap_or(LevelFormat::detect(&content));
+    let (levels, warnings) = LevelCollection::parse_with(&content, from, Strictness::Lenient)
+        .map_err(|err| format!("{}: {err}", input.display()))?;
+    for warning in warnings {
+        eprintln!("{}: warning: {warning}", input.display());
+    }
+
+    let converted = match (to, number) {
+        (LevelFormat::Xsb, None) => levels.to_sok(),
+        (_, Some(number)) => select(&levels, Some(number))?[0].state.to_string_as(to)?,
+        (LevelFormat::Classic, None) if levels.len() == 1 => {
+            select(&levels, Some(1))?[0].state.to_classic()?
+        }
+        (LevelFormat::Classic, None) => {
+            return Err(format!(
+                "the classic format holds a single level but the file has {}, pick one with --level",
+                levels.len()
+            )
+            .into());
+        }
+    };
+
+    match output {
+        Some(path) => {
+            std::fs::write(path, converted).map_err(|err| format!("{}: {err}", path.display()))?
+        }
+        None => print!("{converted}"),
+    }
+    Ok(true)
+}
+
+fn generate(
+    rows: i32,
+    cols: i32,
+    count: usize,
+    attempts: usize,
+    to: LevelFormat,
+) -> CliResult<bool> {
+    if to == LevelFormat::Classic && count != 1 {
+        return Err("the classic format holds a single level, use --to xsb".into());
+    }
+
+    let solver = Solver::new().with_node_limit(100_000);
+    for number in 1..=count {
+        let state = (0..attempts)
+            .map(|_| GameState::random_generate(rows, cols))
+            .find(|state| {
+                state.validate().is_empty() && !state.is_solved() && solver.solve(state).is_ok()
+            })
+            .ok_or_else(|| format!("no solvable level found in {attempts} attempts"))?;
+
+        if count > 1 {
+            println!("; Generated {number}\n");
+        }
+        print!("{}", state.to_string_as(to)?);
+        if number < count {
+            println!();
+        }
+    }
+    Ok(true)
+}
+
+#[derive(Serialize)]
+struct BenchReport {
+    file: PathBuf,
+    levels: Vec<SolveReport>,
+}
+
+fn bench(files: &[PathBuf], args: &SolverArgs, format: OutputFormat) -> CliResult<bool> {
+    let solver = args.solver();
+    let mut reports = Vec::new();
+
+    for file in files {
+        let levels = load(file, Strictness::Lenient)?;
+        let report = BenchReport {
+            file: file.clone(),
+            levels: levels
+                .iter()
+                .map(|level| SolveReport::new(level, &solver))
+                .collect(),
+        };
+        if let OutputFormat::Human = format {
+            for level in &report.levels {
+                println!("{}: {}: {}", file.display(), level.name, level.summary());
+            }
+        }
+        reports.push(report);
+    }
+
+    let all: Vec<&SolveReport> = reports.iter().flat_map(|report| &report.levels).collect();
+    match format {
+        OutputFormat::Human => println!(
+            "solved {} of {} levels in {:.3}s",
+            all.iter().filter(|report| report.solved).count(),
+            all.len(),
+            all.iter().map(|report| report.seconds).sum::<f64>()
+        ),
+        OutputFormat::Json => print_json(&reports)?,
+    }
+
+    Ok(all.iter().all(|report| report.solved))
+}
+
+#[derive(Serialize)]
+struct VerifyReport {
+    solution: PathBuf,
+    valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    moves: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pushes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+fn verify_solutions(
+    solutions: &[PathBuf],
+    levels_dir: &Path,
+    level: Option<usize>,
+    format: OutputFormat,
+) -> CliResult<bool> {
+    let reports: Vec<VerifyReport> = solutions
+        .iter()
+        .map(|solution| {
+            let result = verify_file(solution, levels_dir, level);
+            VerifyReport {
+                solution: solution.clone(),
+                valid: result.is_ok(),
+                moves: result.as_ref().ok().map(|verified| verified.moves),
+                pushes: result.as_ref().ok().map(|verified| verified.pushes),
+                error: result.err(),
+            }
+        })
+        .collect();
+
+    match format {
+        OutputFormat::Human => {
+            for report in &reports {
+                let solution = report.solution.display();
+                match &report.error {
+                    Some(error) => println!("{solution}: {error}"),
+                    None => println!(
+                        "{solution}: ok, {} moves, {} pushes",
+                        report.moves.unwrap_or(0),
+                        report.pushes.unwrap_or(0)
+                    ),
+                }
+            }
+        }
+        OutputFormat::Json => print_json(&reports)?,
+    }
+
+    Ok(reports.iter().all(|report| report.valid))
+}
+
+fn verify_file(
+    solution: &Path,
+    levels_dir: &Path,
+    number: Option<usize>,
+) -> Result<verify::Verified, String> {
+    let stem = solution
+        .file_stem()
+        .ok_or_else(|| "not a file name".to_string())?;
+    let level_path = LEVEL_EXTENSIONS
+        .iter()
+        .map(|ext| levels_dir.join(stem).with_extension(ext))
+        .find(|path| path.is_file() && path != solution)
+        .ok_or_else(|| format!("no level named {stem:?} in {}", levels_dir.display()))?;
+
+    let levels = load_file(&level_path, Strictness::Lenient).map_err(|err| err.to_string())?;
+    let level = match number {
+        None if levels.len() > 1 => {
+            return Err(format!(
+                "{} has {} levels, pick one with --level",
+                level_path.display(),
+                levels.len()
+            ));
+        }
+        number => select(&levels, Some(number.unwrap_or(1))).map_err(|err| err.to_string())?[0],
+    };
+    let lurd = std::fs::read_to_string(solution).map_err(|err| err.to_string())?;
+
+    verify::verify(&level.state, &lurd).map_err(|err| err.to_string())
+}
@@ -0,0 +1,139 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    input::keymap::{Keymap, KeymapError},
+    rendering::theme::{Theme, ThemeError},
+};
+
+/// User settings, read from `config.toml` in the user config directory.
+///
+/// ```toml
+/// theme = "unicode"
+///
+/// [keys]
+/// undo = ["z", "ctrl+z"]
+/// move_left = ["left", "h"]
+/// ```
+///
+/// Every action listed under `[keys]` gets exactly the keys given there,
+/// the others keep their defaults. `theme` is the name of a built-in theme
+/// or the path of a theme file, relative to the config file.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub keymap: Keymap,
+    pub theme: Theme,
+}
+
+/// The file as written by the user, before it is checked.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    theme: Option<String>,
+    #[serde(default)]
+    keys: HashMap<String, Vec<String>>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Keymap(KeymapError),
+    Theme(ThemeError),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "failed to read config: {err}"),
+            ConfigError::Parse(err) => write!(f, "invalid config: {err}"),
+            ConfigError::Keymap(err) => write!(f, "invalid key bindings: {err}"),
+            ConfigError::Theme(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io(err) => Some(err),
+            ConfigError::Parse(err) => Some(err),
+            ConfigError::Keymap(err) => Some(err),
+            ConfigError::Theme(err) => Some(err),
+        }
+    }
+}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(err: std::io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(err: toml::de::Error) -> Self {
+        ConfigError::Parse(err)
+    }
+}
+
+impl From<KeymapError> for ConfigError {
+    fn from(err: KeymapError) -> Self {
+        ConfigError::Keymap(err)
+    }
+}
+
+impl From<ThemeError> for ConfigError {
+    fn from(err: ThemeError) -> Self {
+        ConfigError::Theme(err)
+    }
+}
+
+impl Config {
+    /// Where the config file is looked for, e.g.
+    /// `~/.config/sokoban-rs/config.toml` on Linux.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("sokoban-rs").join("config.toml"))
+    }
+
+    /// Reads the config from the default path. A missing file is not an
+    /// error and gives the default settings.
+    pub fn load() -> Result<Self, ConfigError> {
+        match Self::default_path() {
+            Some(path) if path.exists() => Self::from_file(path),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    pub fn from_file(file_path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let file_path = file_path.as_ref();
+        let dir = file_path.parent().unwrap_or(Path::new(""));
+        Self::parse_in(&std::fs::read_to_string(file_path)?, dir)
+    }
+
+    /// Parses a config, looking for theme files relative to the current
+    /// directory.
+    pub fn parse(content: &str) -> Result<Self, ConfigError> {
+        Self::parse_in(content, Path::new(""))
+    }
+
+    fn parse_in(content: &str, dir: &Path) -> Result<Self, ConfigError> {
+        let file: ConfigFile = toml::from_str(content)?;
+        let theme = match file.theme {
+            Some(name) => match Theme::named(&name) {
+                Some(theme) => theme,
+                None => Theme::from_file(dir.join(name))?,
+            },
+            None => Theme::default(),
+        };
+
+        Ok(Config {
+            keymap: Keymap::default().with_overrides(&file.keys)?,
+            theme,
+        })
+    }
+}
@@ -0,0 +1,431 @@
+use std::path::Path;
+
+use crate::game::{
+    GameState,
+    level::{LevelError, LevelFormat, Strictness},
+};
+
+/// One puzzle of a collection together with its metadata.
+#[derive(Debug, Clone)]
+pub struct Level {
+    /// 1-based position in the collection.
+    pub number: usize,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub comment: Option<String>,
+    pub difficulty: Option<String>,
+    pub state: GameState,
+}
+
+impl Level {
+    /// The title if there is one, otherwise `Level <number>`.
+    pub fn name(&self) -> String {
+        self.title
+            .clone()
+            .unwrap_or_else(|| format!("Level {}", self.number))
+    }
+}
+
+/// A list of levels read from a single file.
+///
+/// Collections use the SOK layout: each board is a block of XSB rows,
+/// followed by optional `Title:`, `Author:`, `Difficulty:` and `Comment:`
+/// lines. A `; name` line right before a board is used as its title. Lines
+/// before the first board describe the collection itself. Files in the
+/// classic format hold exactly one level.
+#[derive(Debug, Clone)]
+pub struct LevelCollection {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub description: Option<String>,
+    levels: Vec<Level>,
+}
+
+/// Which comment a free text line belongs to while parsing.
+enum Section {
+    Header,
+    Level,
+}
+
+impl LevelCollection {
+    pub fn from_file(file_path: impl AsRef<Path>) -> Result<Self, LevelError> {
+        Self::from_file_with(file_path, Strictness::Lenient).map(|(collection, _)| collection)
+    }
+
+    /// Like `from_file`, but also returns the problems that were tolerated
+    /// under the given strictness.
+    pub fn from_file_with(
+        file_path: impl AsRef<Path>,
+        strictness: Strictness,
+    ) -> Result<(Self, Vec<LevelError>), LevelError> {
+        let file_path = file_path.as_ref();
+        let content = std::fs::read_to_string(file_path)?;
+        let format = LevelFormat::from_path(file_path).unwrap_or(LevelFormat::detect(&content));
+
+        let (mut collection, warnings) = Self::parse_with(&content, format, strictness)?;
+
+        if collection.title.is_none() {
+            collection.title = file_path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned());
+        }
+
+        Ok((collection, warnings))
+    }
+
+    /// Parses `content` in the given format. Classic files become a
+    /// collection of one level.
+    pub fn parse_with(
+        content: &str,
+        format: LevelFormat,
+        strictness: Strictness,
+    ) -> Result<(Self, Vec<LevelError>), LevelError> {
+        match format {
+            LevelFormat::Classic => {
+                let (state, warnings) = GameState::parse_classic_with(content, strictness)?;
+                Ok((Self::from(state), warnings))
+            }
+            LevelFormat::Xsb => Ok((Self::parse(content)?, Vec::new())),
+        }
+    }
+
+    /// Parses a collection in SOK layout.
+    pub fn parse(content: &str) -> Result<Self, LevelError> {
+        let mut collection = LevelCollection {
+            title: None,
+            author: None,
+            description: None,
+            levels: Vec::new(),
+        };
+
+        let mut section = Section::Header;
+        let mut board: Vec<&str> = Vec::new();
+        let mut pending_title: Option<String> = None;
+        let mut in_comment = false;
+
+        let lines: Vec<&str> = content.lines().collect();
+        for (index, &line) in lines.iter().enumerate() {
+            let trimmed = line.trim();
+
+            if in_comment {
+                if is_key(trimmed, "comment-end") || is_key(trimmed, "comment_end") {
+                    in_comment = false;
+                } else {
+                    collection.append_comment(&section, trimmed);
+                }
+                continue;
+            }
+
+            if is_board_line(line) {
+                board.push(line);
+                let next_is_board = lines.get(index + 1).is_some_and(|next| is_board_line(next));
+                if !next_is_board {
+                    let number = collection.levels.len() + 1;
+                    let state = GameState::parse_xsb(&board.join("\n")).map_err(|source| {
+                        LevelError::InLevel {
+                            number,
+                            source: Box::new(source),
+                        }
+                    })?;
+                    collection.levels.push(Level {
+                        number,
+                        title: pending_title.take(),
+                        author: None,
+                        comment: None,
+                        difficulty: None,
+                        state,
+                    });
+                    board.clear();
+                    section = Section::Level;
+                }
+                continue;
+            }
+
+            if trimmed.is_empty() {
+                continue;
+            }
+
+            if let Some(name) = trimmed.strip_prefix(';') {
+                pending_title = Some(name.trim().to_string());
+                continue;
+            }
+
+            if let Some((key, value)) = trimmed.split_once(':') {
+                let value = value.trim();
+                let value = (!value.is_empty()).then(|| value.to_string());
+                let handled = match key.trim().to_ascii_lowercase().as_str() {
+                    "title" => {
+                        *collection.title_mut(&section) = value;
+                        true
+                    }
+                    "author" => {
+                        *collection.author_mut(&section) = value;
+                        true
+                    }
+                    "difficulty" => {
+                        if let Some(level) = collection.levels.last_mut() {
+                            level.difficulty = value;
+                        }
+                        true
+                    }
+                    "comment" => {
+                        match value {
+                            Some(value) => collection.append_comment(&section, &value),
+                            None => in_comment = true,
+                        }
+                        true
+                    }
+                    _ => false,
+                };
+                if handled {
+                    continue;
+                }
+            }
+
+            collection.append_comment(&section, trimmed);
+        }
+
+        if collection.levels.is_empty() {
+            return Err(LevelError::NoLevels);
+        }
+
+        Ok(collection)
+    }
+
+    /// Joins collections into one, numbering the levels on. Levels without
+    /// a title are named after the collection they came from, so levels
+    /// from different files can still be told apart.
+    pub fn concat(
+        title: Option<String>,
+        collections: impl IntoIterator<Item = LevelCollection>,
+    ) -> Result<Self, LevelError> {
+        let mut joined = LevelCollection {
+            title,
+            author: None,
+            description: None,
+            levels: Vec::new(),
+        };
+
+        for collection in collections {
+            let several = collection.levels.len() > 1;
+            for mut level in collection.levels {
+                if level.title.is_none()
+                    && let Some(title) = &collection.title
+                {
+                    level.title = Some(if several {
+                        format!("{title} {}", level.number)
+                    } else {
+                        title.clone()
+                    });
+                }
+                level.number = joined.levels.len() + 1;
+                joined.levels.push(level);
+            }
+        }
+
+        if joined.levels.is_empty() {
+            return Err(LevelError::NoLevels);
+        }
+        Ok(joined)
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    /// The level with the given 1-based number.
+    pub fn get(&self, number: usize) -> Option<&Level> {
+        number
+            .checked_sub(1)
+            .and_then(|index| self.levels.get(index))
+    }
+
+    /// The first level whose title matches, ignoring case.
+    pub fn find(&self, title: &str) -> Option<&Level> {
+        self.levels.iter().find(|level| {
+            level
+                .title
+                .as_deref()
+                .is_some_and(|t| t.eq_ignore_ascii_case(title))
+        })
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Level> {
+        self.levels.iter()
+    }
+
+    /// Writes the collection in SOK layout, with each level's title as a
+    /// `; name` line above its board.
+    pub fn to_sok(&self) -> String {
+        let mut content = String::new();
+
+        if let Some(title) = &self.title {
+            content.push_str(&format!("Title: {title}\n"));
+        }
+        if let Some(author) = &self.author {
+            content.push_str(&format!("Author: {author}\n"));
+        }
+        if let Some(description) = &self.description {
+            content.push_str(description);
+            content.push('\n');
+        }
+
+        for level in &self.levels {
+            if !content.is_empty() {
+                content.push('\n');
+            }
+            if let Some(title) = &level.title {
+                content.push_str(&format!("; {title}\n\n"));
+            }
+            content.push_str(&level.state.to_xsb());
+            if let Some(author) = &level.author {
+                content.push_str(&format!("Author: {author}\n"));
+            }
+            if let Some(difficulty) = &level.difficulty {
+                content.push_str(&format!("Difficulty: {difficulty}\n"));
+            }
+            if let Some(comment) = &level.comment {
+                content.push_str(&format!("Comment:\n{comment}\nComment-End:\n"));
+            }
+        }
+
+        content
+    }
+
+    fn title_mut(&mut self, section: &Section) -> &mut Option<String> {
+        match (section, self.levels.last_mut()) {
+            (Section::Level, Some(level)) => &mut level.title,
+            _ => &mut self.title,
+        }
+    }
+
+    fn author_mut(&mut self, section: &Section) -> &mut Option<String> {
+        match (section, self.levels.last_mut()) {
+            (Section::Level, Some(level)) => &mut level.author,
+            _ => &mut self.author,
+        }
+    }
+
+    fn append_comment(&mut self, section: &Section, text: &str) {
+        let comment = match (section, self.levels.last_mut()) {
+            (Section::Level, Some(level)) => &mut level.comment,
+            _ => &mut self.description,
+        };
+        match comment {
+            Some(comment) => {
+                comment.push('\n');
+                comment.push_str(text);
+            }
+            None => *comment = Some(text.to_string()),
+        }
+    }
+}
+
+impl From<GameState> for LevelCollection {
+    fn from(state: GameState) -> Self {
+        LevelCollection {
+            title: None,
+            author: None,
+            description: None,
+            levels: vec![Level {
+                number: 1,
+                title: None,
+                author: None,
+                comment: None,
+                difficulty: None,
+                state,
+            }],
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a LevelCollection {
+    type Item = &'a Level;
+    type IntoIter = std::slice::Iter<'a, Level>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.levels.iter()
+    }
+}
+
+/// A row of an XSB board: only board characters and at least one wall.
+fn is_board_line(line: &str) -> bool {
+    line.contains('#')
+        && line
+            .chars()
+            .all(|c| "#@+$*.-_ |".contains(c) || c.is_ascii_digit())
+}
+
+fn is_key(line: &str, key: &str) -> bool {
+    line.strip_suffix(':')
+        .is_some_and(|name| name.trim().eq_ignore_ascii_case(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::testing::{CORRIDOR, xsb};
+
+    const PACK: &str = "\
+Title: Test Pack
+Author: Someone
+A small pack for testing.
+
+; First
+#####
+#@$.#
+#####
+Difficulty: easy
+
+######
+#@$ .#
+######
+Title: Second
+Author: Other
+Comment:
+multi
+line
+Comment-End:
+";
+
+    #[test]
+    fn parse_reads_levels_and_their_metadata() {
+        let collection = LevelCollection::parse(PACK).unwrap();
+
+        assert_eq!(collection.title.as_deref(), Some("Test Pack"));
+        assert_eq!(collection.author.as_deref(), Some("Someone"));
+        assert_eq!(
+            collection.description.as_deref(),
+            Some("A small pack for testing.")
+        );
+        assert_eq!(collection.len(), 2);
+
+        let first = collection.get(1).unwrap();
+        assert_eq!(first.name(), "First");
+        assert_eq!(first.difficulty.as_deref(), Some("easy"));
+        assert_eq!(first.state, xsb(CORRIDOR));
+
+        let second = collection.find("second").unwrap();
+        assert_eq!(second.number, 2);
+        assert_eq!(second.author.as_deref(), Some("Other"));
+        assert_eq!(second.comment.as_deref(), Some("multi\nline"));
+        assert!(collection.get(3).is_none());
+    }
+
+    #[test]
+    fn parse_reports_the_level_a_board_error_is_in() {
+        let content = format!("{CORRIDOR}\n\n#####\n#@$ #\n#####");
+        assert!(matches!(
+            LevelCollection::parse(&content),
+            Err(LevelError::InLevel { number: 2, .. })
+        ));
+        assert!(matches!(
+            LevelCollection::parse("Title: Nothing here"),
+            Err(LevelError::NoLevels)
+        ));
+    }
+}
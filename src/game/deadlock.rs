@@ -0,0 +1,509 @@
+use std::{collections::VecDeque, fmt};
+
+use crate::game::{
+    GameState,
+    position::{BitSet, Board, DIRECTIONS, Position},
+    squares::SquareKind,
+};
+
+/// Why a position can no longer be solved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeadlockKind {
+    /// A box stands on a square from which no target can be reached.
+    DeadSquare((i32, i32)),
+    /// These boxes block each other and none of them can move again.
+    Frozen(Vec<(i32, i32)>),
+    /// These boxes seal off an area the player cannot enter, and the area
+    /// still needs boxes moved in or out of it.
+    Corral(Vec<(i32, i32)>),
+    /// Not every box can be given its own target; these boxes are left over.
+    Unmatched(Vec<(i32, i32)>),
+}
+
+impl fmt::Display for DeadlockKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeadlockKind::DeadSquare(pos) => {
+                write!(f, "the box at {pos:?} can never reach a target")
+            }
+            DeadlockKind::Frozen(boxes) => {
+                write!(f, "{} boxes are frozen off target", boxes.len())
+            }
+            DeadlockKind::Corral(boxes) => write!(
+                f,
+                "{} boxes wall off an area that can never be finished",
+                boxes.len()
+            ),
+            DeadlockKind::Unmatched(boxes) => write!(
+                f,
+                "{} boxes have no target left they can reach",
+                boxes.len()
+            ),
+        }
+    }
+}
+
+/// Finds the boxes that can never move again because of the box at `index`.
+///
+/// A box is frozen when it is blocked both horizontally and vertically. An
+/// axis is blocked by a wall on either side, by dead squares on both sides, or
+/// by a neighbouring box that is itself frozen. While a box is being checked
+/// it counts as a wall for its neighbours, which keeps the recursion finite.
+///
+/// Returns every box of the frozen group, or an empty list if the box at
+/// `index` can still be moved.
+pub fn frozen_boxes(
+    board: &Board,
+    boxes: &BitSet,
+    dead: impl Fn(usize) -> bool,
+    index: usize,
+) -> Vec<usize> {
+    let mut checker = FreezeChecker {
+        board,
+        boxes,
+        dead: &dead,
+        in_progress: BitSet::new(board.len()),
+        frozen: Vec::new(),
+    };
+
+    if checker.is_frozen(index) {
+        checker.frozen
+    } else {
+        Vec::new()
+    }
+}
+
+/// Whether moving the box at `index` onto its cell froze a box that is not on
+/// a target. Frozen boxes that all sit on targets are fine.
+pub fn is_freeze_deadlock(
+    board: &Board,
+    boxes: &BitSet,
+    dead: impl Fn(usize) -> bool,
+    index: usize,
+) -> bool {
+    frozen_boxes(board, boxes, dead, index)
+        .into_iter()
+        .any(|index| !board.is_target(index))
+}
+
+struct FreezeChecker<'a, D: Fn(usize) -> bool> {
+    board: &'a Board,
+    boxes: &'a BitSet,
+    dead: &'a D,
+    in_progress: BitSet,
+    frozen: Vec<usize>,
+}
+
+impl<D: Fn(usize) -> bool> FreezeChecker<'_, D> {
+    fn is_frozen(&mut self, index: usize) -> bool {
+        self.in_progress.insert(index);
+
+        let frozen = self.is_axis_blocked(index, (0, -1), (0, 1))
+            && self.is_axis_blocked(index, (-1, 0), (1, 0));
+
+        if frozen {
+            self.frozen.push(index);
+        } else {
+            self.in_progress.remove(index); // Only frozen boxes keep acting as walls
+        }
+        frozen
+    }
+
+    fn is_axis_blocked(&mut self, index: usize, first: (i32, i32), second: (i32, i32)) -> bool {
+        let (Some(a), Some(b)) = (
+            self.board.neighbor(index, first),
+            self.board.neighbor(index, second),
+        ) else {
+            return true; // A wall on either side
+        };
+
+        if self.in_progress.contains(a) || self.in_progress.contains(b) {
+            return true;
+        }
+
+        if (self.dead)(a) && (self.dead)(b) {
+            return true;
+        }
+
+        (self.boxes.contains(a) && self.is_frozen(a))
+            || (self.boxes.contains(b) && self.is_frozen(b))
+    }
+}
+
+/// Static data for checking positions of one level for deadlocks.
+pub struct DeadlockDetector<'a> {
+    board: &'a Board,
+    dead: BitSet,
+    /// For every target, the cells from which a box could be pushed onto it
+    /// if there were no other boxes.
+    reach: Vec<(usize, BitSet)>,
+}
+
+impl<'a> DeadlockDetector<'a> {
+    /// `dead` holds the squares a box can never be pushed off towards a target.
+    pub fn new(board: &'a Board, dead: BitSet) -> Self {
+        let reach = (0..board.len())
+            .filter(|&index| board.is_floor(index) && board.is_target(index))
+            .map(|target| (target, pull_region(board, target)))
+            .collect();
+
+        DeadlockDetector { board, dead, reach }
+    }
+
+    pub fn is_dead(&self, index: usize) -> bool {
+        self.dead.contains(index)
+    }
+
+    /// Looks for any kind of deadlock in `position`.
+    pub fn check(&self, position: &Position) -> Option<DeadlockKind> {
+        let board = self.board;
+        let boxes = position.boxes();
+        let to_positions =
+            |cells: Vec<usize>| cells.into_iter().map(|i| board.position(i)).collect();
+
+        if let Some(index) = boxes
+            .iter()
+            .find(|&index| !board.is_target(index) && self.dead.contains(index))
+        {
+            return Some(DeadlockKind::DeadSquare(board.position(index)));
+        }
+
+        let mut frozen = BitSet::new(board.len());
+        for index in boxes.iter() {
+            if frozen.contains(index) {
+                continue;
+            }
+            let group = frozen_boxes(board, boxes, |i| self.dead.contains(i), index);
+            if group.iter().any(|&i| !board.is_target(i)) {
+                return Some(DeadlockKind::Frozen(to_positions(group)));
+            }
+            for i in group {
+                frozen.insert(i);
+            }
+        }
+
+        if let Some(corral) = self.corral_deadlock(position) {
+            return Some(DeadlockKind::Corral(to_positions(corral)));
+        }
+
+        let unmatched = self.unmatched_boxes(boxes, &frozen);
+        if !unmatched.is_empty() {
+            return Some(DeadlockKind::Unmatched(to_positions(unmatched)));
+        }
+
+        None
+    }
+
+    /// Cheaper check used during search after a box was pushed from `from`
+    /// to `moved`. `reachable` is where the player could walk before the
+    /// push. Only the pushed box is checked for freezing, corrals only when
+    /// the box now borders an area the player could not reach, and the
+    /// matching only when the box lost a target it could reach before.
+    pub fn is_push_deadlock(
+        &self,
+        position: &Position,
+        from: usize,
+        moved: usize,
+        reachable: &BitSet,
+    ) -> bool {
+        let board = self.board;
+        let boxes = position.boxes();
+        if is_freeze_deadlock(board, boxes, |i| self.dead.contains(i), moved) {
+            return true;
+        }
+
+        // Only a corral next to the pushed box can have been sealed by it
+        let corral_starts: Vec<usize> = DIRECTIONS
+            .iter()
+            .filter_map(|&direction| board.neighbor(moved, direction))
+            .filter(|&next| next != from && !boxes.contains(next) && !reachable.contains(next))
+            .collect();
+        if !corral_starts.is_empty() && self.sealed_corral(position, corral_starts).is_some() {
+            return true;
+        }
+
+        let lost_target = self
+            .reach
+            .iter()
+            .any(|(_, region)| region.contains(from) != region.contains(moved));
+        lost_target
+            && !self
+                .unmatched_boxes(boxes, &BitSet::new(board.len()))
+                .is_empty()
+    }
+
+    /// Finds an area the player cannot reach whose boundary boxes can never be
+    /// pushed, even if every other box got out of the way, and which still
+    /// has an empty target inside or a boundary box off target. Returns the
+    /// boundary boxes of the first such area.
+    pub fn corral_deadlock(&self, position: &Position) -> Option<Vec<usize>> {
+        self.sealed_corral(position, 0..self.board.len())
+    }
+
+    /// Like `corral_deadlock`, but only looks at the areas containing one of
+    /// the `starts` cells.
+    fn sealed_corral(
+        &self,
+        position: &Position,
+        starts: impl IntoIterator<Item = usize>,
+    ) -> Option<Vec<usize>> {
+        let board = self.board;
+        let boxes = position.boxes();
+        let reachable = board.reachable(boxes, position.player());
+        let mut seen = BitSet::new(board.len());
+
+        for start in starts {
+            if !board.is_floor(start)
+                || boxes.contains(start)
+                || reachable.contains(start)
+                || seen.contains(start)
+            {
+                continue;
+            }
+
+            // Flood the corral and collect the boxes on its border
+            let mut corral = BitSet::new(board.len());
+            let mut border = BitSet::new(board.len());
+            let mut queue = VecDeque::from([start]);
+            corral.insert(start);
+            seen.insert(start);
+
+            while let Some(index) = queue.pop_front() {
+                for direction in DIRECTIONS {
+                    let Some(next) = board.neighbor(index, direction) else {
+                        continue;
+                    };
+                    if boxes.contains(next) {
+                        border.insert(next);
+                    } else if !corral.contains(next) {
+                        corral.insert(next);
+                        seen.insert(next);
+                        queue.push_back(next);
+                    }
+                }
+            }
+
+            let settled = border.iter().all(|index| board.is_target(index))
+                && corral.iter().all(|index| !board.is_target(index));
+            if !settled && self.is_sealed(&corral, &border) {
+                return Some(border.iter().collect());
+            }
+        }
+
+        None
+    }
+
+    /// Whether none of the `border` boxes can be pushed from outside the
+    /// corral without being blocked by another border box, a wall or a dead
+    /// square.
+    fn is_sealed(&self, corral: &BitSet, border: &BitSet) -> bool {
+        let board = self.board;
+
+        border.iter().all(|index| {
+            DIRECTIONS.iter().all(|&(dr, dc)| {
+                let behind = board.neighbor(index, (-dr, -dc));
+                let dest = board.neighbor(index, (dr, dc));
+                let (Some(behind), Some(dest)) = (behind, dest) else {
+                    return true;
+                };
+                corral.contains(behind)
+                    || border.contains(behind)
+                    || border.contains(dest)
+                    || self.dead.contains(dest)
+            })
+        })
+    }
+
+    /// Matches boxes to targets they can reach, each target taking at most
+    /// one box, and returns the boxes left without a target. Boxes in
+    /// `frozen` can only keep the target they already stand on.
+    pub fn unmatched_boxes(&self, boxes: &BitSet, frozen: &BitSet) -> Vec<usize> {
+        let box_cells: Vec<usize> = boxes.iter().collect();
+        let candidates: Vec<Vec<usize>> = box_cells
+            .iter()
+            .map(|&cell| {
+                self.reach
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (target, region))| {
+                        if frozen.contains(cell) {
+                            *target == cell
+                        } else {
+                            region.contains(cell)
+                        }
+                    })
+                    .map(|(slot, _)| slot)
+                    .collect()
+            })
+            .collect();
+
+        let mut owner = vec![usize::MAX; self.reach.len()];
+        let mut unmatched = Vec::new();
+
+        for (slot, &cell) in box_cells.iter().enumerate() {
+            let mut visited = vec![false; self.reach.len()];
+            if !augment(slot, &candidates, &mut owner, &mut visited) {
+                unmatched.push(cell);
+            }
+        }
+
+        unmatched
+    }
+}
+
+/// Tries to find a target for `slot`, moving earlier boxes to other targets
+/// when needed (Kuhn's augmenting path algorithm).
+fn augment(
+    slot: usize,
+    candidates: &[Vec<usize>],
+    owner: &mut [usize],
+    visited: &mut [bool],
+) -> bool {
+    for &target in &candidates[slot] {
+        if visited[target] {
+            continue;
+        }
+        visited[target] = true;
+        if owner[target] == usize::MAX || augment(owner[target], candidates, owner, visited) {
+            owner[target] = slot;
+            return true;
+        }
+    }
+    false
+}
+
+/// Every cell from which a lone box can be pushed onto `target`.
+fn pull_region(board: &Board, target: usize) -> BitSet {
+    let mut region = BitSet::new(board.len());
+    for (index, distance) in board.pull_distances([target]).into_iter().enumerate() {
+        if distance.is_some() {
+            region.insert(index);
+        }
+    }
+    region
+}
+
+impl GameState {
+    /// Positions of every box that can no longer be moved without running
+    /// into a deadlock.
+    pub fn frozen_boxes(&self) -> Vec<(i32, i32)> {
+        let board = Board::new(self);
+        let Some(position) = self.to_position(&board) else {
+            return Vec::new();
+        };
+        let dead = self.dead_squares(&board);
+
+        let mut frozen = BitSet::new(board.len());
+        for index in position.boxes().iter() {
+            if frozen.contains(index) {
+                continue;
+            }
+            for index in frozen_boxes(&board, position.boxes(), |i| dead.contains(i), index) {
+                frozen.insert(index);
+            }
+        }
+
+        frozen.iter().map(|index| board.position(index)).collect()
+    }
+
+    /// Explains why the position can no longer be solved, or `None` if no
+    /// deadlock was found.
+    pub fn deadlock_reason(&self) -> Option<DeadlockKind> {
+        let board = Board::new(self);
+        let position = self.to_position(&board)?;
+        DeadlockDetector::new(&board, self.dead_squares(&board)).check(&position)
+    }
+
+    /// Cells a box can never be brought to a target from, as a set of
+    /// `board` indices.
+    pub(crate) fn dead_squares(&self, board: &Board) -> BitSet {
+        let mut dead = BitSet::new(board.len());
+        for index in 0..board.len() {
+            if self.squares.get_index(index) != SquareKind::Live {
+                dead.insert(index);
+            }
+        }
+        dead
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::testing::{mission, xsb};
+
+    fn sorted(mut cells: Vec<(i32, i32)>) -> Vec<(i32, i32)> {
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn boxes_blocking_each_other_are_frozen() {
+        let block = xsb("#######\n#  .. #\n# $$  #\n# $$  #\n#@ .. #\n#######");
+        assert_eq!(
+            sorted(block.frozen_boxes()),
+            [(2, 2), (2, 3), (3, 2), (3, 3)]
+        );
+        assert!(block.is_deadlock());
+
+        let pair = xsb("######\n#.$$.#\n#@   #\n######");
+        assert_eq!(sorted(pair.frozen_boxes()), [(1, 2), (1, 3)]);
+    }
+
+    #[test]
+    fn frozen_boxes_on_targets_are_no_deadlock() {
+        let state = xsb("######\n#**  #\n#@   #\n######");
+        assert_eq!(sorted(state.frozen_boxes()), [(1, 1), (1, 2)]);
+        assert!(!state.is_deadlock());
+    }
+
+    #[test]
+    fn movable_boxes_are_not_frozen() {
+        let state = mission("mission1.txt");
+        assert!(state.frozen_boxes().is_empty());
+        assert!(!state.is_deadlock());
+    }
+
+    #[test]
+    fn deadlocks_come_with_their_reason() {
+        let corner = xsb("#####\n#$  #\n# @.#\n#####");
+        assert_eq!(
+            corner.deadlock_reason(),
+            Some(DeadlockKind::DeadSquare((1, 1)))
+        );
+
+        let pair = xsb("######\n#.$$.#\n#@   #\n######");
+        let Some(DeadlockKind::Frozen(boxes)) = pair.deadlock_reason() else {
+            panic!("expected frozen boxes");
+        };
+        assert_eq!(sorted(boxes), [(1, 2), (1, 3)]);
+    }
+
+    #[test]
+    fn boxes_sealing_off_a_target_are_a_corral() {
+        // Neither box can be pushed, and the player cannot get behind them
+        let state = xsb("########\n#   #  #\n# . $$ #\n# @# . #\n########");
+        assert!(state.frozen_boxes().is_empty());
+
+        let Some(DeadlockKind::Corral(boxes)) = state.deadlock_reason() else {
+            panic!("expected a corral");
+        };
+        assert_eq!(sorted(boxes), [(2, 4), (2, 5)]);
+    }
+
+    #[test]
+    fn boxes_sharing_their_only_target_are_unmatched() {
+        // Both boxes can only be pushed along the top row, to one target
+        let state = xsb("#######\n# $ $.#\n#     #\n#    .#\n#@    #\n#######");
+        assert_eq!(
+            state.deadlock_reason(),
+            Some(DeadlockKind::Unmatched(vec![(1, 4)]))
+        );
+
+        let board = Board::new(&state);
+        let detector = DeadlockDetector::new(&board, state.dead_squares(&board));
+        let position = state.to_position(&board).unwrap();
+        let unmatched = detector.unmatched_boxes(position.boxes(), &BitSet::new(board.len()));
+        assert_eq!(unmatched.len(), 1);
+    }
+}
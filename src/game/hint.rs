@@ -0,0 +1,61 @@
+use std::fmt;
+
+use crate::game::{
+    GameState,
+    solver::{Push, Solution, SolveError, Solver},
+};
+
+/// The next push of a solution, without the rest of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hint {
+    pub push: Push,
+    /// The cells the player walks through to get behind the box, ending
+    /// there. Empty if the player is already in place.
+    pub path: Vec<(i32, i32)>,
+}
+
+impl Hint {
+    /// The first push of `solution`, or `None` if it has no pushes because
+    /// the position is already solved.
+    pub fn from_solution(solution: &Solution) -> Option<Self> {
+        let &push = solution.pushes.first()?;
+        let path = solution
+            .steps
+            .iter()
+            .copied()
+            .take_while(|&cell| cell != push.box_position)
+            .collect();
+        Some(Hint { push, path })
+    }
+
+    /// Where the box ends up after the push.
+    pub fn destination(&self) -> (i32, i32) {
+        let (row, col) = self.push.box_position;
+        let (dr, dc) = self.push.direction;
+        (row + dr, col + dc)
+    }
+}
+
+impl fmt::Display for Hint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let direction = match self.push.direction {
+            (-1, 0) => "up",
+            (1, 0) => "down",
+            (0, -1) => "left",
+            _ => "right",
+        };
+        write!(
+            f,
+            "Push the box at {:?} {direction}",
+            self.push.box_position
+        )
+    }
+}
+
+impl GameState {
+    /// Solves the position with `solver` and returns the first push of the
+    /// solution, or `None` if the position is already solved.
+    pub fn hint(&self, solver: &Solver) -> Result<Option<Hint>, SolveError> {
+        Ok(Hint::from_solution(&solver.solve(self)?))
+    }
+}
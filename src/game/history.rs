@@ -0,0 +1,305 @@
+use std::fmt;
+
+use crate::game::{GameState, MoveResult};
+
+/// One step of the player, with whether it pushed a box.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    pub direction: (i32, i32),
+    pub push: bool,
+}
+
+impl Move {
+    /// The LURD letter for this move: `l`, `u`, `r` or `d` for plain moves and
+    /// the uppercase letter for pushes.
+    pub fn to_char(self) -> char {
+        let c = match self.direction {
+            (0, -1) => 'l',
+            (-1, 0) => 'u',
+            (0, 1) => 'r',
+            _ => 'd',
+        };
+        if self.push { c.to_ascii_uppercase() } else { c }
+    }
+
+    pub fn from_char(c: char) -> Option<Self> {
+        let direction = match c.to_ascii_lowercase() {
+            'l' => (0, -1),
+            'u' => (-1, 0),
+            'r' => (0, 1),
+            'd' => (1, 0),
+            _ => return None,
+        };
+        Some(Move {
+            direction,
+            push: c.is_ascii_uppercase(),
+        })
+    }
+}
+
+/// How many moves a LURD string may expand to. Real solutions never come
+/// close, and anything longer is most likely a corrupt file.
+pub const MAX_MOVES: usize = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseLurdError {
+    /// A character that is not a move. `offset` is 0-based, in characters.
+    UnexpectedChar { offset: usize, found: char },
+    /// The string expands to more than `MAX_MOVES` moves, going over at
+    /// the repeat count or move at `offset`.
+    TooLong { offset: usize },
+}
+
+impl fmt::Display for ParseLurdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseLurdError::UnexpectedChar { offset, found } => {
+                write!(f, "unexpected {found:?} at offset {offset} in LURD string")
+            }
+            ParseLurdError::TooLong { offset } => write!(
+                f,
+                "LURD string is longer than {MAX_MOVES} moves at offset {offset}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseLurdError {}
+
+/// Parses a LURD string into moves. Whitespace is ignored and a number in
+/// front of a letter repeats it, so `3r` is the same as `rrr`. Strings that
+/// expand to more than `MAX_MOVES` moves are rejected.
+pub fn parse_lurd(lurd: &str) -> Result<Vec<Move>, ParseLurdError> {
+    let mut moves = Vec::new();
+    let mut count = 0usize;
+    let mut count_start = None;
+
+    for (offset, c) in lurd.chars().enumerate() {
+        if c.is_whitespace() {
+            continue;
+        }
+        let too_long = ParseLurdError::TooLong {
+            offset: count_start.unwrap_or(offset),
+        };
+        if let Some(digit) = c.to_digit(10) {
+            count_start.get_or_insert(offset);
+            // Checked so a huge count is an error instead of an overflow
+            count = count
+                .checked_mul(10)
+                .and_then(|count| count.checked_add(digit as usize))
+                .filter(|&count| count <= MAX_MOVES)
+                .ok_or(too_long)?;
+            continue;
+        }
+        let mv = Move::from_char(c).ok_or(ParseLurdError::UnexpectedChar { offset, found: c })?;
+        let repeat = count.max(1);
+        if moves.len() + repeat > MAX_MOVES {
+            return Err(too_long);
+        }
+        moves.extend(std::iter::repeat_n(mv, repeat));
+        count = 0;
+        count_start = None;
+    }
+
+    Ok(moves)
+}
+
+/// Every move made so far plus the ones that were undone, so they can be
+/// redone. Moves past `cursor` form the redo branch and are dropped as soon as
+/// a different move is made.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    moves: Vec<Move>,
+    cursor: usize,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A history whose whole content is waiting to be redone, e.g. an
+    /// imported solution that can be stepped through with `redo`.
+    pub fn from_lurd(lurd: &str) -> Result<Self, ParseLurdError> {
+        Ok(History {
+            moves: parse_lurd(lurd)?,
+            cursor: 0,
+        })
+    }
+
+    /// Records a move that was just made. The redo branch is kept if the
+    /// move is its next one, and dropped otherwise.
+    pub fn record(&mut self, mv: Move) {
+        if self.moves.get(self.cursor) != Some(&mv) {
+            self.moves.truncate(self.cursor);
+            self.moves.push(mv);
+        }
+        self.cursor += 1;
+    }
+
+    /// Moves `state` one step in `direction` and records it if it was legal.
+    pub fn play(&mut self, state: &mut GameState, direction: (i32, i32)) -> MoveResult {
+        let result = state.try_move(direction);
+        if result != MoveResult::Blocked {
+            self.record(Move {
+                direction,
+                push: result == MoveResult::Pushed,
+            });
+        }
+        result
+    }
+
+    /// Takes back the last move, pulling the box back if it was a push.
+    pub fn undo(&mut self, state: &mut GameState) -> bool {
+        let Some(cursor) = self.cursor.checked_sub(1) else {
+            return false;
+        };
+        let Move { direction, push } = self.moves[cursor];
+        let (dr, dc) = direction;
+        let (r, c) = state.player_position;
+
+        if push
+            && let Some(box_pos) = state
+                .box_positions
+                .iter_mut()
+                .find(|pos| **pos == (r + dr, c + dc))
+        {
+            *box_pos = (r, c);
+        }
+        state.player_position = (r - dr, c - dc);
+        self.cursor = cursor;
+        true
+    }
+
+    /// Makes the next move of the redo branch again. Fails without moving
+    /// if the move is blocked, or if it would push a box when it was
+    /// recorded as a plain move or the other way round, as undo relies on
+    /// the recorded flag.
+    pub fn redo(&mut self, state: &mut GameState) -> bool {
+        let Some(&mv) = self.moves.get(self.cursor) else {
+            return false;
+        };
+        let (r, c) = state.player_position;
+        let (dr, dc) = mv.direction;
+        if state.box_positions.contains(&(r + dr, c + dc)) != mv.push
+            || state.try_move(mv.direction) == MoveResult::Blocked
+        {
+            return false;
+        }
+        self.cursor += 1;
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.moves.clear();
+        self.cursor = 0;
+    }
+
+    /// The moves made so far, not counting the redo branch.
+    pub fn moves(&self) -> &[Move] {
+        &self.moves[..self.cursor]
+    }
+
+    /// The moves that were undone and can be redone.
+    pub fn redo_moves(&self) -> &[Move] {
+        &self.moves[self.cursor..]
+    }
+
+    pub fn move_count(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn push_count(&self) -> usize {
+        self.moves().iter().filter(|mv| mv.push).count()
+    }
+
+    /// The moves made so far in LURD notation.
+    pub fn to_lurd(&self) -> String {
+        self.moves().iter().map(|mv| mv.to_char()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::testing::{CORRIDOR, xsb};
+
+    #[test]
+    fn parse_lurd_expands_counts_and_skips_whitespace() {
+        let lurd: String = parse_lurd("3r L\n2u")
+            .unwrap()
+            .into_iter()
+            .map(Move::to_char)
+            .collect();
+        assert_eq!(lurd, "rrrLuu");
+    }
+
+    #[test]
+    fn parse_lurd_reports_unexpected_characters() {
+        assert_eq!(
+            parse_lurd("rx"),
+            Err(ParseLurdError::UnexpectedChar {
+                offset: 1,
+                found: 'x'
+            })
+        );
+    }
+
+    #[test]
+    fn parse_lurd_rejects_counts_that_are_too_large() {
+        assert_eq!(
+            parse_lurd("r99999999999999999999999r"),
+            Err(ParseLurdError::TooLong { offset: 1 })
+        );
+        assert_eq!(
+            parse_lurd("1000000r l"),
+            Err(ParseLurdError::TooLong { offset: 9 })
+        );
+        assert_eq!(
+            parse_lurd("1000000r").map(|moves| moves.len()),
+            Ok(MAX_MOVES)
+        );
+    }
+
+    #[test]
+    fn redo_refuses_a_push_recorded_as_a_walk() {
+        let mut state = xsb(CORRIDOR);
+        let mut history = History::from_lurd("r").unwrap();
+
+        assert!(!history.redo(&mut state));
+        assert_eq!(state.player_position, (1, 1));
+        assert_eq!(state.box_positions, [(1, 2)]);
+        assert_eq!(history.move_count(), 0);
+    }
+
+    #[test]
+    fn undo_pulls_back_a_redone_push() {
+        let mut state = xsb(CORRIDOR);
+        let mut history = History::from_lurd("R").unwrap();
+
+        assert!(history.redo(&mut state));
+        assert_eq!(history.push_count(), 1);
+        assert!(history.undo(&mut state));
+        assert_eq!(state.player_position, (1, 1));
+        assert_eq!(state.box_positions, [(1, 2)]);
+    }
+
+    #[test]
+    fn record_keeps_the_redo_branch_only_for_its_next_move() {
+        let mut state = xsb("#####\n# @ #\n#####");
+        let right = Move::from_char('r').unwrap();
+        let left = Move::from_char('l').unwrap();
+        let mut history = History::new();
+        history.play(&mut state, right.direction);
+        history.play(&mut state, left.direction);
+        history.undo(&mut state);
+        history.undo(&mut state);
+
+        history.record(right);
+        assert_eq!(history.redo_moves(), [left]);
+
+        history.record(right);
+        assert!(history.redo_moves().is_empty());
+        assert_eq!(history.to_lurd(), "rr");
+    }
+}
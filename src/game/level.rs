@@ -0,0 +1,688 @@
+use std::{collections::VecDeque, fmt, path::Path, str::FromStr};
+
+use crate::game::GameState;
+
+/// The longest row a run-length encoded line may expand to, so a corrupt
+/// count is reported instead of filling memory.
+pub const MAX_RUN_ROW: usize = 1_000;
+
+/// Everything that can be wrong with a level file.
+#[derive(Debug)]
+pub enum LevelError {
+    /// The file could not be read.
+    Io(std::io::Error),
+    /// The first line is not a `rows cols` pair.
+    InvalidHeader {
+        line: String,
+    },
+    /// The header does not match the size of the grid below it.
+    HeaderMismatch {
+        header: (i32, i32),
+        actual: (i32, i32),
+    },
+    /// A character that is not part of the level alphabet. `line` and
+    /// `column` are 1-based and count the header line.
+    UnknownTile {
+        tile: char,
+        line: usize,
+        column: usize,
+    },
+    NoPlayer,
+    MultiplePlayers {
+        first: (i32, i32),
+        second: (i32, i32),
+    },
+    BoxTargetMismatch {
+        boxes: usize,
+        targets: usize,
+    },
+    /// The player can walk off the map starting from `position`.
+    Unenclosed {
+        position: (i32, i32),
+    },
+    /// A collection file without a single board in it.
+    NoLevels,
+    /// A level inside a collection failed to load.
+    InLevel {
+        number: usize,
+        source: Box<LevelError>,
+    },
+    /// The cell at `position` cannot be expressed in the target format.
+    Unrepresentable {
+        position: (i32, i32),
+        format: LevelFormat,
+    },
+    /// A run-length count at `line` (1-based) makes a row longer than
+    /// `MAX_RUN_ROW` cells.
+    RunTooLong {
+        line: usize,
+    },
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelError::Io(err) => write!(f, "failed to read level: {err}"),
+            LevelError::InvalidHeader { line } => {
+                write!(f, "expected a `rows cols` header, found {line:?}")
+            }
+            LevelError::HeaderMismatch { header, actual } => write!(
+                f,
+                "header says {}x{} but the grid is {}x{}",
+                header.0, header.1, actual.0, actual.1
+            ),
+            LevelError::UnknownTile { tile, line, column } => {
+                write!(f, "unknown tile {tile:?} at line {line}, column {column}")
+            }
+            LevelError::NoPlayer => write!(f, "the level has no player"),
+            LevelError::MultiplePlayers { first, second } => write!(
+                f,
+                "the level has more than one player, at {first:?} and {second:?}"
+            ),
+            LevelError::BoxTargetMismatch { boxes, targets } => {
+                write!(f, "the level has {boxes} boxes but {targets} targets")
+            }
+            LevelError::Unenclosed { position } => {
+                write!(f, "the player can walk off the map at {position:?}")
+            }
+            LevelError::NoLevels => write!(f, "the file does not contain any level"),
+            LevelError::InLevel { number, source } => write!(f, "level {number}: {source}"),
+            LevelError::Unrepresentable { position, format } => {
+                write!(f, "the cell at {position:?} cannot be written as {format}")
+            }
+            LevelError::RunTooLong { line } => write!(
+                f,
+                "a run-length count at line {line} makes a row longer than {MAX_RUN_ROW} cells"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LevelError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LevelError::Io(err) => Some(err),
+            LevelError::InLevel { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for LevelError {
+    fn from(err: std::io::Error) -> Self {
+        LevelError::Io(err)
+    }
+}
+
+/// On-disk level formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LevelFormat {
+    /// This repo's own format: a `rows cols` header followed by a grid of
+    /// `/` walls, `-` floor, `0` player, `1` boxes and `2` targets.
+    Classic,
+    /// The community standard XSB format: `#` walls, `@` player, `+` player
+    /// on goal, `$` boxes, `*` boxes on goal, `.` goals and space, `-` or `_`
+    /// for floor. Rows may be run-length encoded.
+    Xsb,
+}
+
+impl LevelFormat {
+    /// Picks the format from a file extension, if it is a known one.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "xsb" | "sok" => Some(LevelFormat::Xsb),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from the content: classic levels start with a
+    /// numeric header line.
+    pub fn detect(content: &str) -> Self {
+        let first = content.lines().find(|line| !line.trim().is_empty());
+        if first.and_then(parse_header).is_some() {
+            LevelFormat::Classic
+        } else {
+            LevelFormat::Xsb
+        }
+    }
+}
+
+impl fmt::Display for LevelFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelFormat::Classic => write!(f, "classic"),
+            LevelFormat::Xsb => write!(f, "xsb"),
+        }
+    }
+}
+
+impl FromStr for LevelFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "classic" | "txt" => Ok(LevelFormat::Classic),
+            "xsb" | "sok" => Ok(LevelFormat::Xsb),
+            other => Err(format!("unknown level format {other:?}")),
+        }
+    }
+}
+
+/// How strictly the `rows cols` header of classic levels is checked
+/// against the grid below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strictness {
+    /// A header that does not match the grid is an error.
+    Strict,
+    /// A header that does not match the grid is reported as a warning and
+    /// the size of the grid wins.
+    #[default]
+    Lenient,
+}
+
+/// Collects tiles row by row and checks the result once the grid is done.
+#[derive(Default)]
+struct TileGrid {
+    player_position: Option<(i32, i32)>,
+    box_positions: Vec<(i32, i32)>,
+    target_positions: Vec<(i32, i32)>,
+    walls: Vec<(i32, i32)>,
+    row_lengths: Vec<i32>,
+}
+
+impl TileGrid {
+    fn set_player(&mut self, pos: (i32, i32)) -> Result<(), LevelError> {
+        if let Some(first) = self.player_position {
+            return Err(LevelError::MultiplePlayers { first, second: pos });
+        }
+        self.player_position = Some(pos);
+        Ok(())
+    }
+
+    fn size(&self) -> (i32, i32) {
+        (
+            self.row_lengths.len() as i32,
+            self.row_lengths.iter().copied().max().unwrap_or(0),
+        )
+    }
+
+    fn finish(self) -> Result<GameState, LevelError> {
+        let player_position = self.player_position.ok_or(LevelError::NoPlayer)?;
+
+        if self.box_positions.len() != self.target_positions.len() {
+            return Err(LevelError::BoxTargetMismatch {
+                boxes: self.box_positions.len(),
+                targets: self.target_positions.len(),
+            });
+        }
+
+        let map_size = self.size();
+        let state = GameState::new(
+            player_position,
+            self.box_positions,
+            self.target_positions,
+            self.walls,
+            map_size,
+        );
+
+        let row_lengths = &self.row_lengths;
+        let inside = |(r, c): (i32, i32)| {
+            r >= 0 && (r as usize) < row_lengths.len() && c >= 0 && c < row_lengths[r as usize]
+        };
+        if let Some(position) = state.find_leak(inside) {
+            return Err(LevelError::Unenclosed { position });
+        }
+
+        Ok(state)
+    }
+}
+
+impl GameState {
+    /// Loads a level, picking the format from the file extension and falling
+    /// back to looking at the content.
+    pub fn from_file(file_path: impl AsRef<Path>) -> Result<Self, LevelError> {
+        Self::from_file_with(file_path, Strictness::Lenient).map(|(state, _)| state)
+    }
+
+    /// Like `from_file`, but also returns the problems that were tolerated
+    /// under the given strictness.
+    pub fn from_file_with(
+        file_path: impl AsRef<Path>,
+        strictness: Strictness,
+    ) -> Result<(Self, Vec<LevelError>), LevelError> {
+        let file_path = file_path.as_ref();
+        let content = std::fs::read_to_string(file_path)?;
+        match LevelFormat::from_path(file_path).unwrap_or(LevelFormat::detect(&content)) {
+            LevelFormat::Classic => Self::parse_classic_with(&content, strictness),
+            LevelFormat::Xsb => Ok((Self::parse_xsb(&content)?, Vec::new())),
+        }
+    }
+
+    /// Parses a level in whichever format the content looks like.
+    pub fn parse(content: &str) -> Result<Self, LevelError> {
+        Self::parse_as(content, LevelFormat::detect(content))
+    }
+
+    pub fn parse_as(content: &str, format: LevelFormat) -> Result<Self, LevelError> {
+        match format {
+            LevelFormat::Classic => Self::parse_classic(content),
+            LevelFormat::Xsb => Self::parse_xsb(content),
+        }
+    }
+
+    /// Parses a level in the `rows cols` header format, where `/` is a wall,
+    /// `-` is floor, `0` is the player, `1` a box and `2` a target. A header
+    /// that does not match the grid is tolerated.
+    pub fn parse_classic(content: &str) -> Result<Self, LevelError> {
+        Self::parse_classic_with(content, Strictness::Lenient).map(|(state, _)| state)
+    }
+
+    /// Parses a classic level, returning the header problems that were
+    /// tolerated under the given strictness as warnings.
+    pub fn parse_classic_with(
+        content: &str,
+        strictness: Strictness,
+    ) -> Result<(Self, Vec<LevelError>), LevelError> {
+        let mut lines: Vec<&str> = content.lines().collect();
+        while lines.last().is_some_and(|line| line.trim().is_empty()) {
+            lines.pop(); // Trailing blank lines are not part of the grid
+        }
+        let mut lines = lines.into_iter();
+
+        let header_line = lines.next().unwrap_or_default();
+        let header = parse_header(header_line).ok_or_else(|| LevelError::InvalidHeader {
+            line: header_line.to_string(),
+        })?;
+
+        let mut grid = TileGrid::default();
+
+        for (r, line_content) in lines.enumerate() {
+            grid.row_lengths.push(line_content.chars().count() as i32);
+            for (c, char) in line_content.chars().enumerate() {
+                let pos = (r as i32, c as i32);
+                match char {
+                    '/' => grid.walls.push(pos),
+                    '0' => grid.set_player(pos)?,
+                    '1' => grid.box_positions.push(pos),
+                    '2' => grid.target_positions.push(pos),
+                    '-' => { /* Road, do nothing */ }
+                    tile => {
+                        return Err(LevelError::UnknownTile {
+                            tile,
+                            line: r + 2,
+                            column: c + 1,
+                        });
+                    }
+                }
+            }
+        }
+
+        let mut warnings = Vec::new();
+        if header != grid.size() {
+            let mismatch = LevelError::HeaderMismatch {
+                header,
+                actual: grid.size(),
+            };
+            match strictness {
+                Strictness::Strict => return Err(mismatch),
+                Strictness::Lenient => warnings.push(mismatch),
+            }
+        }
+
+        Ok((grid.finish()?, warnings))
+    }
+
+    /// Parses a single level in XSB format. Leading and trailing blank lines
+    /// are ignored; every other line must be part of the grid.
+    pub fn parse_xsb(content: &str) -> Result<Self, LevelError> {
+        let lines: Vec<(usize, &str)> = content.lines().enumerate().collect();
+        let first = lines.iter().position(|(_, line)| !line.trim().is_empty());
+        let last = lines.iter().rposition(|(_, line)| !line.trim().is_empty());
+
+        let mut grid = TileGrid::default();
+        let (Some(first), Some(last)) = (first, last) else {
+            return grid.finish();
+        };
+
+        for &(line_index, line_content) in &lines[first..=last] {
+            for row in expand_rle(line_content, line_index + 1)? {
+                let r = grid.row_lengths.len() as i32;
+                grid.row_lengths.push(row.len() as i32);
+                for (c, (char, column)) in row.into_iter().enumerate() {
+                    let pos = (r, c as i32);
+                    match char {
+                        '#' => grid.walls.push(pos),
+                        '@' => grid.set_player(pos)?,
+                        '+' => {
+                            grid.set_player(pos)?;
+                            grid.target_positions.push(pos);
+                        }
+                        '$' => grid.box_positions.push(pos),
+                        '*' => {
+                            grid.box_positions.push(pos);
+                            grid.target_positions.push(pos);
+                        }
+                        '.' => grid.target_positions.push(pos),
+                        ' ' | '-' | '_' => { /* Floor */ }
+                        tile => {
+                            return Err(LevelError::UnknownTile {
+                                tile,
+                                line: line_index + 1,
+                                column,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        grid.finish()
+    }
+
+    pub fn to_string_as(&self, format: LevelFormat) -> Result<String, LevelError> {
+        match format {
+            LevelFormat::Classic => self.to_classic(),
+            LevelFormat::Xsb => Ok(self.to_xsb()),
+        }
+    }
+
+    /// Writes the level in the `rows cols` header format. Fails if a box or
+    /// the player stands on a target, which that format cannot express.
+    pub fn to_classic(&self) -> Result<String, LevelError> {
+        let mut rows = Vec::new();
+
+        for r in 0..self.map_size.0 {
+            let mut row = String::new();
+            for c in 0..self.map_size.1 {
+                let pos = (r, c);
+                let is_target = self.target_positions.contains(&pos);
+                let tile = if self.walls.contains(&pos) {
+                    '/'
+                } else if pos == self.player_position {
+                    '0'
+                } else if self.box_positions.contains(&pos) {
+                    '1'
+                } else if is_target {
+                    '2'
+                } else {
+                    '-'
+                };
+                if is_target && tile != '2' {
+                    return Err(LevelError::Unrepresentable {
+                        position: pos,
+                        format: LevelFormat::Classic,
+                    });
+                }
+                row.push(tile);
+            }
+            rows.push(row.trim_end_matches('-').to_string());
+        }
+
+        let cols = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        let mut content = format!("{} {}\n", rows.len(), cols);
+        for row in rows {
+            content.push_str(&row);
+            content.push('\n');
+        }
+        Ok(content)
+    }
+
+    /// Writes the level in XSB format, with spaces for floor.
+    pub fn to_xsb(&self) -> String {
+        let mut content = String::new();
+
+        for r in 0..self.map_size.0 {
+            let mut row = String::new();
+            for c in 0..self.map_size.1 {
+                let pos = (r, c);
+                let is_target = self.target_positions.contains(&pos);
+                row.push(if self.walls.contains(&pos) {
+                    '#'
+                } else if pos == self.player_position {
+                    if is_target { '+' } else { '@' }
+                } else if self.box_positions.contains(&pos) {
+                    if is_target { '*' } else { '$' }
+                } else if is_target {
+                    '.'
+                } else {
+                    ' '
+                });
+            }
+            content.push_str(row.trim_end());
+            content.push('\n');
+        }
+
+        content
+    }
+
+    /// Checks a level for problems that make it unplayable: the player being
+    /// able to walk off the map and a different number of boxes and targets.
+    /// Returns every problem found, so an empty list means the level is fine.
+    pub fn validate(&self) -> Vec<LevelError> {
+        let mut problems = Vec::new();
+
+        if self.box_positions.len() != self.target_positions.len() {
+            problems.push(LevelError::BoxTargetMismatch {
+                boxes: self.box_positions.len(),
+                targets: self.target_positions.len(),
+            });
+        }
+
+        let (rows, cols) = self.map_size;
+        let (player_row, player_col) = self.player_position;
+        if player_row < 0 || player_row >= rows || player_col < 0 || player_col >= cols {
+            problems.push(LevelError::Unenclosed {
+                position: self.player_position,
+            });
+        } else if let Some(position) =
+            self.find_leak(|(r, c)| r >= 0 && r < rows && c >= 0 && c < cols)
+        {
+            problems.push(LevelError::Unenclosed { position });
+        }
+
+        problems
+    }
+
+    /// Flood-fills the floor from the player and returns the first cell from
+    /// which the player could step onto a cell that is not `inside` the map.
+    fn find_leak(&self, inside: impl Fn((i32, i32)) -> bool) -> Option<(i32, i32)> {
+        let mut visited = vec![false; (self.map_size.0 * self.map_size.1).max(0) as usize];
+        let mut queue = VecDeque::from([self.player_position]);
+        visited[(self.player_position.0 * self.map_size.1 + self.player_position.1) as usize] =
+            true;
+
+        while let Some((r, c)) = queue.pop_front() {
+            for (dr, dc) in [(0, 1), (1, 0), (0, -1), (-1, 0)] {
+                let next = (r + dr, c + dc);
+                if !inside(next) {
+                    return Some((r, c));
+                }
+                let index = (next.0 * self.map_size.1 + next.1) as usize;
+                if !visited[index] && !self.walls.contains(&next) {
+                    visited[index] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Expands a run-length encoded XSB line such as `4#|#@$.#` into plain rows.
+/// Every cell comes with the 1-based column of the character it was expanded
+/// from, so errors can point into the original line. `line_number` is only
+/// used in the error for a row that expands past `MAX_RUN_ROW` cells.
+fn expand_rle(line: &str, line_number: usize) -> Result<Vec<Vec<(char, usize)>>, LevelError> {
+    if !line.chars().any(|c| c.is_ascii_digit() || c == '|') {
+        return Ok(vec![line.chars().zip(1..).collect()]);
+    }
+
+    let too_long = || LevelError::RunTooLong { line: line_number };
+    let mut rows = vec![Vec::new()];
+    let mut count = 0usize;
+
+    for (char, column) in line.chars().zip(1..) {
+        if let Some(digit) = char.to_digit(10) {
+            count = count
+                .checked_mul(10)
+                .and_then(|count| count.checked_add(digit as usize))
+                .filter(|&count| count <= MAX_RUN_ROW)
+                .ok_or_else(too_long)?;
+            continue;
+        }
+        if char == '|' {
+            rows.push(Vec::new());
+        } else if let Some(row) = rows.last_mut() {
+            let count = count.max(1);
+            if row.len() + count > MAX_RUN_ROW {
+                return Err(too_long());
+            }
+            row.extend(std::iter::repeat_n((char, column), count));
+        }
+        count = 0;
+    }
+
+    Ok(rows)
+}
+
+fn parse_header(line: &str) -> Option<(i32, i32)> {
+    let mut parts = line.split_whitespace();
+    let rows = parts.next()?.parse().ok()?;
+    let cols = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((rows, cols))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAIN: &str = "#######\n#@ $ .#\n#######";
+    const CLASSIC_GRID: &str = "/////\n/012/\n/////";
+
+    #[test]
+    fn parse_xsb_expands_run_length_rows() {
+        let plain = GameState::parse_xsb(PLAIN).unwrap();
+        let rle = GameState::parse_xsb("7#|#@-$-.#|7#").unwrap();
+
+        assert_eq!(rle, plain);
+        assert_eq!(rle.map_size, (3, 7));
+        assert_eq!(rle.box_positions, [(1, 3)]);
+    }
+
+    #[test]
+    fn parse_xsb_accepts_run_length_lines_among_plain_ones() {
+        let mixed = GameState::parse_xsb("7#\n#@2-$.#\n#######").unwrap();
+        assert_eq!(
+            mixed,
+            GameState::parse_xsb("#######\n#@  $.#\n#######").unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_xsb_rejects_runs_that_are_too_long() {
+        for content in ["99999999999999999999#", "600#600#"] {
+            assert!(
+                matches!(
+                    GameState::parse_xsb(content),
+                    Err(LevelError::RunTooLong { .. })
+                ),
+                "{content}"
+            );
+        }
+        assert!(matches!(
+            GameState::parse_xsb("#@$.#\n1001#"),
+            Err(LevelError::RunTooLong { line: 2 })
+        ));
+    }
+
+    #[test]
+    fn parse_xsb_reports_unknown_tiles() {
+        assert!(matches!(
+            GameState::parse_xsb("#####\n#@$x#\n#####"),
+            Err(LevelError::UnknownTile {
+                tile: 'x',
+                line: 2,
+                column: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn parse_xsb_reports_source_columns_in_run_length_lines() {
+        assert!(matches!(
+            GameState::parse_xsb("#@$.#|#x#"),
+            Err(LevelError::UnknownTile {
+                tile: 'x',
+                line: 1,
+                column: 8
+            })
+        ));
+        assert!(matches!(
+            GameState::parse_xsb("5#\n#@$.3x"),
+            Err(LevelError::UnknownTile {
+                tile: 'x',
+                line: 2,
+                column: 6
+            })
+        ));
+    }
+
+    #[test]
+    fn a_header_mismatch_is_an_error_only_when_strict() {
+        let content = format!("3 6\n{CLASSIC_GRID}");
+
+        assert!(matches!(
+            GameState::parse_classic_with(&content, Strictness::Strict),
+            Err(LevelError::HeaderMismatch {
+                header: (3, 6),
+                actual: (3, 5)
+            })
+        ));
+
+        let (state, warnings) =
+            GameState::parse_classic_with(&content, Strictness::Lenient).unwrap();
+        assert_eq!(state.map_size, (3, 5));
+        assert!(matches!(
+            warnings[..],
+            [LevelError::HeaderMismatch {
+                header: (3, 6),
+                actual: (3, 5)
+            }]
+        ));
+    }
+
+    #[test]
+    fn a_matching_header_gives_no_warnings() {
+        let content = format!("3 5\n{CLASSIC_GRID}\n\n");
+        for strictness in [Strictness::Strict, Strictness::Lenient] {
+            let (_, warnings) = GameState::parse_classic_with(&content, strictness).unwrap();
+            assert!(warnings.is_empty());
+        }
+        assert!(matches!(
+            GameState::parse_classic(&format!("3\n{CLASSIC_GRID}")),
+            Err(LevelError::InvalidHeader { .. })
+        ));
+    }
+
+    #[test]
+    fn validate_reports_every_problem() {
+        let state = GameState::new((1, 1), vec![(1, 2)], vec![], vec![(0, 0)], (3, 3));
+        let problems = state.validate();
+
+        assert!(matches!(
+            problems[..],
+            [
+                LevelError::BoxTargetMismatch {
+                    boxes: 1,
+                    targets: 0
+                },
+                LevelError::Unenclosed { .. }
+            ]
+        ));
+        assert!(GameState::parse_xsb(PLAIN).unwrap().validate().is_empty());
+    }
+}
@@ -1,472 +1,850 @@
 use std::{
-    collections::{HashMap, HashSet, VecDeque},
-    sync::Mutex,
-    thread,
+    collections::VecDeque,
+    path::PathBuf,
+    time::{Duration, Instant},
 };
 
 use rand::random_range;
 
 use crate::{
-    input::{self},
-    rendering::FrontEnd,
+    game::{
+        collection::{Level, LevelCollection},
+        deadlock::DeadlockKind,
+        history::{History, Move, ParseLurdError},
+        squares::SquareMap,
+        stats::{LevelInfo, Stats},
+    },
+    input::InputEvent,
+    progress::{Completion, Progress, level_key},
+    rendering::{FrontEnd, FrontEndEvent, GameEvent},
+    session::{Session, SessionError},
 };
 
-#[derive(Debug, Clone)]
+pub mod collection;
+pub mod deadlock;
+pub mod hint;
+pub mod history;
+pub mod level;
+pub mod position;
+pub mod route;
+pub mod solver;
+pub mod squares;
+pub mod stats;
+pub mod verify;
+
+#[cfg(test)]
+pub(crate) mod testing;
+
+/// What happened when the player tried to move one cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveResult {
+    Walked,
+    Pushed,
+    Blocked,
+}
+
+/// How `Game::run` drives the player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    /// The player is controlled from the keyboard.
+    #[default]
+    Interactive,
+    /// The solver's solution is replayed one step at a time.
+    Autoplay,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct GameState {
     pub player_position: (i32, i32),
     pub box_positions: Vec<(i32, i32)>,
     pub target_positions: Vec<(i32, i32)>,
     pub walls: Vec<(i32, i32)>,
     pub map_size: (i32, i32),
-    pub dead_pos: Vec<(i32, i32)>,
-    pub box_route: Vec<(i32, i32)>,
-    pub player_route: Vec<(i32, i32)>,
+    pub squares: SquareMap,
 }
 
 impl GameState {
-    pub fn from_file(file_path: std::path::PathBuf) -> Self {
-        let content = std::fs::read_to_string(file_path).expect("Failed to read game state file");
-
-        let lines = content.lines();
-
-        let mut player_position = (0, 0);
-        let mut box_positions = Vec::new();
-        let mut target_positions = Vec::new();
-        let mut walls = Vec::new();
-
-        let mut rows = 0;
-        let mut cols = 0;
-
-        for (r, line_content) in lines.skip(1).enumerate() {
-            rows += 1;
-            cols = (line_content.len() as i32).max(cols);
-            for (c, char) in line_content.chars().enumerate() {
-                let pos = (r as i32, c as i32);
-                match char {
-                    '/' => walls.push(pos),
-                    '0' => player_position = pos,
-                    '1' => box_positions.push(pos),
-                    '2' => target_positions.push(pos),
-                    '-' => { /* Road, do nothing */ }
-                    _ => { /* Ignore other characters */ }
-                }
-            }
-        }
-
+    /// Builds a state from its parts and runs the static analysis on it.
+    pub fn new(
+        player_position: (i32, i32),
+        box_positions: Vec<(i32, i32)>,
+        target_positions: Vec<(i32, i32)>,
+        walls: Vec<(i32, i32)>,
+        map_size: (i32, i32),
+    ) -> Self {
         let mut state = GameState {
             player_position,
             box_positions,
             target_positions,
             walls,
-            map_size: (rows, cols),
-            dead_pos: Vec::new(),
-            box_route: Vec::new(),
-            player_route: Vec::new(),
+            map_size,
+            squares: SquareMap::default(),
         };
 
-        state.generate_deadlock_positions();
-
-        state.generate_route();
+        state.squares = SquareMap::new(&state);
 
         state
     }
 
+    /// Builds a random `rows` x `cols` room with an outer wall, two boxes,
+    /// two targets and a few inner walls, all on distinct cells. The result
+    /// is not guaranteed to be solvable.
     pub fn random_generate(rows: i32, cols: i32) -> Self {
-        let player_position = (random_range(0..rows), random_range(0..cols));
-        let box_positions = vec![
-            (random_range(0..rows), random_range(0..cols)),
-            (random_range(0..rows), random_range(0..cols)),
-        ];
-        let target_positions = vec![
-            (random_range(0..rows), random_range(0..cols)),
-            (random_range(0..rows), random_range(0..cols)),
-        ];
-        let walls = vec![
-            (random_range(0..rows), random_range(0..cols)),
-            (random_range(0..rows), random_range(0..cols)),
-        ];
+        // Leave room inside the outer wall for the player, boxes and targets
+        let rows = rows.max(4);
+        let cols = cols.max(5);
 
-        let mut state = GameState {
+        let mut walls = Vec::new();
+        for r in 0..rows {
+            for c in 0..cols {
+                if r == 0 || r == rows - 1 || c == 0 || c == cols - 1 {
+                    walls.push((r, c));
+                }
+            }
+        }
+
+        let mut free: Vec<(i32, i32)> = (1..rows - 1)
+            .flat_map(|r| (1..cols - 1).map(move |c| (r, c)))
+            .collect();
+        let inner_walls = (free.len() - 5) / 5;
+        let mut take = || free.swap_remove(random_range(0..free.len()));
+
+        let player_position = take();
+        let box_positions = vec![take(), take()];
+        let target_positions = vec![take(), take()];
+        for _ in 0..inner_walls {
+            walls.push(take());
+        }
+
+        GameState::new(
             player_position,
             box_positions,
             target_positions,
             walls,
-            map_size: (rows, cols),
-            dead_pos: Vec::new(),
-            box_route: Vec::new(),
-            player_route: Vec::new(),
-        };
+            (rows, cols),
+        )
+    }
 
-        state.generate_deadlock_positions();
+    pub fn is_deadlock(&self) -> bool {
+        self.deadlock_reason().is_some()
+    }
 
-        state.generate_route();
+    pub fn in_bounds(&self, pos: (i32, i32)) -> bool {
+        pos.0 >= 0 && pos.0 < self.map_size.0 && pos.1 >= 0 && pos.1 < self.map_size.1
+    }
 
-        state
+    fn is_open(&self, pos: (i32, i32)) -> bool {
+        self.in_bounds(pos) && !self.walls.contains(&pos)
     }
 
-    fn generate_deadlock_positions(&mut self) {
-        let mut pos_map =
-            HashMap::<(i32, i32), bool>::from_iter(self.walls.iter().map(|&pos| (pos, true)));
-        let mut deadlock_positions = Vec::<(i32, i32)>::from_iter(self.walls.iter().cloned());
-        let mut queue = VecDeque::<(i32, i32)>::new();
+    /// Moves the player one cell in `direction` if the rules allow it,
+    /// pushing a box when there is one in the way and room behind it.
+    pub fn try_move(&mut self, (dr, dc): (i32, i32)) -> MoveResult {
+        let (player_row, player_col) = self.player_position;
+        let next = (player_row + dr, player_col + dc);
 
-        for &pos in &self.target_positions {
-            if !pos_map.contains_key(&pos) {
-                queue.push_back(pos);
+        if !self.is_open(next) {
+            return MoveResult::Blocked;
+        }
+
+        if let Some(box_index) = self.box_positions.iter().position(|&pos| pos == next) {
+            let box_next = (next.0 + dr, next.1 + dc);
+            if !self.is_open(box_next) || self.box_positions.contains(&box_next) {
+                return MoveResult::Blocked;
             }
-            pos_map.insert(pos, true);
+            self.box_positions[box_index] = box_next;
+            self.player_position = next;
+            return MoveResult::Pushed;
         }
 
-        let (map_rows, map_cols) = self.map_size;
+        self.player_position = next;
+        MoveResult::Walked
+    }
 
-        while let Some(live_pos) = queue.pop_front() {
-            let (r, c) = live_pos;
+    pub fn is_solved(&self) -> bool {
+        // Check if all boxes are on target positions
+        self.box_positions
+            .iter()
+            .all(|pos| self.target_positions.contains(pos))
+    }
+}
 
-            if !pos_map.contains_key(&(r, c)) {
-                // Mark as processed
-                pos_map.insert((r, c), true);
-            }
+/// How often front ends get a `GameEvent::Tick`.
+const TICK: Duration = Duration::from_millis(50);
 
-            for (dr, dc) in &[(0, 1), (1, 0), (0, -1), (-1, 0)] {
-                let (box_prev_pos_row, box_prev_pos_col) = (r + dr, c + dc);
-                let (player_prev_pos_row, player_prev_pos_col) =
-                    (box_prev_pos_row + dr, box_prev_pos_col + dc);
-
-                if box_prev_pos_row < 0
-                    || box_prev_pos_row >= map_rows
-                    || box_prev_pos_col < 0
-                    || box_prev_pos_col >= map_cols
-                    || player_prev_pos_row < 0
-                    || player_prev_pos_row >= map_rows
-                    || player_prev_pos_col < 0
-                    || player_prev_pos_col >= map_cols
-                {
-                    continue; // Out of bounds
-                }
+/// How many states the solver may explore for a hint, so a hint never takes
+/// long to come.
+const HINT_NODE_LIMIT: usize = 100_000;
 
-                if let Some(true) = pos_map.get(&(box_prev_pos_row, box_prev_pos_col)) {
-                    continue; // Already processed
-                }
+/// Heuristic weight of the hint solver. A hint only needs a solution, not the
+/// shortest one, and weighting finds one within the node limit far more often.
+const HINT_WEIGHT: u32 = 3;
 
-                if !self.walls.contains(&(box_prev_pos_row, box_prev_pos_col))
-                    && !self
-                        .walls
-                        .contains(&(player_prev_pos_row, player_prev_pos_col))
-                {
-                    queue.push_back((box_prev_pos_row, box_prev_pos_col));
-                }
-            }
-        }
+/// What a search running in the background is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Search {
+    Autoplay,
+    Hint,
+}
 
-        for r in 0..map_rows {
-            for c in 0..map_cols {
-                if !pos_map.contains_key(&(r, c)) {
-                    deadlock_positions.push((r, c)); // Add all unprocessed positions
-                }
-            }
-        }
+pub struct Game<F: FrontEnd> {
+    pub state: GameState,
+    pub front_end: F,
+    pub mode: Mode,
+    /// How long autoplay waits between two steps.
+    pub autoplay_delay: Duration,
+    /// The solver asked for hints.
+    pub hint_solver: solver::Solver,
+    /// Whether to open the level menu when the game starts.
+    pub start_in_menu: bool,
+    /// The player's records, updated and saved whenever a level is solved.
+    pub progress: Progress,
+    levels: LevelCollection,
+    level_index: usize,
+    initial_state: GameState,
+    history: History,
+    /// The deadlock of the current position, if there is one.
+    deadlock: Option<DeadlockKind>,
+    /// Moves autoplay still has to make on this level, or `None` if it has
+    /// no solution yet.
+    plan: Option<VecDeque<(i32, i32)>>,
+    /// The search for autoplay or a hint, running on a worker thread so the
+    /// game keeps answering input.
+    solving: Option<(Search, solver::BackgroundSolve)>,
+    /// Time spent on the current level while it was unsolved.
+    elapsed: Duration,
+    /// Whether the moves on this level come from `load_solution`, so solving
+    /// it is not the player's own record.
+    replaying: bool,
+}
 
-        self.dead_pos = deadlock_positions;
+impl<F: FrontEnd> Game<F> {
+    pub fn new(state: GameState, mode: Mode) -> Self {
+        Self::with_levels(LevelCollection::from(state), 1, mode)
     }
 
-    pub fn generate_route(&mut self) {
-        self.box_route.clear();
-
-        let box_condition_fn = |state: &GameState,
-                                (map_rows, map_cols): (&i32, &i32),
-                                box_pos: (&i32, &i32),
-                                direction: (&i32, &i32)| {
-            let (box_row, box_col) = box_pos;
-            let (dir_row, dir_col) = direction;
+    /// Starts at the level with the given 1-based `number`, clamped to the
+    /// size of the collection.
+    pub fn with_levels(levels: LevelCollection, number: usize, mode: Mode) -> Self {
+        let level_index = number.clamp(1, levels.len()) - 1;
+        let state = levels
+            .iter()
+            .nth(level_index)
+            .map(|level| level.state.clone());
+        let state = state.expect("a level collection is never empty");
 
-            // Check if the box can be pushed in the given direction
-            let (new_box_row, new_box_col) = (box_row + dir_row, box_col + dir_col);
-            let (player_row, player_col) = (box_row - dir_row, box_col - dir_col);
+        Game {
+            initial_state: state.clone(),
+            state,
+            front_end: F::default(),
+            mode,
+            autoplay_delay: Duration::from_millis(100),
+            hint_solver: solver::Solver::new()
+                .with_node_limit(HINT_NODE_LIMIT)
+                .with_weight(HINT_WEIGHT),
+            start_in_menu: false,
+            progress: Progress::default(),
+            levels,
+            level_index,
+            history: History::new(),
+            deadlock: None,
+            plan: None,
+            solving: None,
+            elapsed: Duration::ZERO,
+            replaying: false,
+        }
+    }
 
-            let mut surrounded_by_walls_count = 0;
+    /// The level currently being played.
+    pub fn level(&self) -> &Level {
+        self.levels
+            .get(self.level_index + 1)
+            .expect("the level index always stays in range")
+    }
 
-            for (dr, dc) in &[(0, 1), (1, 0), (0, -1), (-1, 0)] {
-                let (adj_row, adj_col) = (player_row + dr, player_col + dc);
-                if state.walls.contains(&(adj_row, adj_col)) {
-                    surrounded_by_walls_count += 1;
-                }
-            }
+    /// Switches to the next level of the collection. Returns `false` when
+    /// the current level is the last one.
+    pub fn next_level(&mut self) -> bool {
+        self.go_to_level(self.level_index + 2)
+    }
 
-            state.walls.contains(&(new_box_row, new_box_col))
-                || state.walls.contains(&(player_row, player_col))
-                || state.dead_pos.contains(&(new_box_row, new_box_col))
-                || surrounded_by_walls_count >= 3
+    /// Starts the level with the given 1-based number from the beginning.
+    /// Returns `false` if there is no such level.
+    pub fn go_to_level(&mut self, number: usize) -> bool {
+        let Some(level) = self.levels.get(number) else {
+            return false;
         };
 
-        let player_condition_fn = |state: &GameState,
-                                   (map_rows, map_cols): (&i32, &i32),
-                                   player_pos: (&i32, &i32),
-                                   direction: (&i32, &i32)| {
-            let (player_row, player_col) = player_pos;
-            let (dir_row, dir_col) = direction;
+        self.state = level.state.clone();
+        self.initial_state = self.state.clone();
+        self.level_index = number - 1;
+        self.history.clear();
+        self.elapsed = Duration::ZERO;
+        self.replaying = false;
+        self.level_loaded();
+        true
+    }
 
-            // Check if the player can move in the given direction
-            let new_player_pos = (player_row + dir_row, player_col + dir_col);
+    /// Everything needed to continue the current game later. `source` is
+    /// where the levels were loaded from.
+    pub fn to_session(&self, source: PathBuf) -> Session {
+        let moves = self.history.moves().iter().chain(self.history.redo_moves());
+        Session {
+            source,
+            level: self.level_index + 1,
+            level_key: level_key(&self.initial_state),
+            board: self.state.to_xsb(),
+            moves: moves.map(|mv| mv.to_char()).collect(),
+            cursor: self.history.move_count(),
+            elapsed_ms: self.elapsed.as_millis() as u64,
+        }
+    }
 
-            state.walls.contains(&new_player_pos) || state.box_positions.contains(&new_player_pos)
+    /// Continues a saved game, before `run`. The level is looked up by its
+    /// content, so it is found even if the collection was reordered.
+    pub fn resume(&mut self, session: &Session) -> Result<(), SessionError> {
+        let matches = |level: &Level| level_key(&level.state) == session.level_key;
+        let level = match self.levels.get(session.level) {
+            Some(level) if matches(level) => level,
+            _ => self
+                .levels
+                .iter()
+                .find(|level| matches(level))
+                .ok_or_else(|| SessionError::LevelMissing(session.source.clone()))?,
         };
 
-        for pos in &self.box_positions {
-            for target in &self.target_positions {
-                if self.box_positions.contains(target) {
-                    continue; // Skip if the box is already on the target
-                }
-                let route = self.find_route_to_target(*pos, *target, box_condition_fn);
-                if !route.is_empty() {
-                    self.box_route.extend(route);
-                }
+        let mut state = level.state.clone();
+        let mut history = History::from_lurd(&session.moves)?;
+        for _ in 0..session.cursor {
+            if !history.redo(&mut state) {
+                return Err(SessionError::Mismatch);
             }
         }
+        if state.to_xsb() != session.board {
+            return Err(SessionError::Mismatch);
+        }
 
-        self.player_route.clear();
-
-        if self.box_route.len() > 1 {
-            let (first_box_pos_row, first_box_pos_col) = self.box_route[0];
-            let (second_box_pos_row, second_box_pos_col) = self.box_route[1];
+        self.level_index = level.number - 1;
+        self.initial_state = level.state.clone();
+        self.state = state;
+        self.history = history;
+        self.elapsed = session.elapsed();
+        self.replaying = false;
+        Ok(())
+    }
 
-            let (dr, dc) = (
-                first_box_pos_row - second_box_pos_row,
-                first_box_pos_col - second_box_pos_col,
-            );
+    /// Every level of the collection with how far the player got on it.
+    pub fn level_infos(&self) -> Vec<LevelInfo> {
+        self.levels
+            .iter()
+            .map(|level| {
+                let record = self.progress.get(&level.state);
+                LevelInfo {
+                    number: level.number,
+                    name: level.name(),
+                    size: level.state.map_size,
+                    boxes: level.state.box_positions.len(),
+                    best: record.and_then(|record| record.best()),
+                    best_time: record.and_then(|record| record.best_time()),
+                    state: level.state.clone(),
+                }
+            })
+            .collect()
+    }
 
-            let (player_target_row, player_target_col) =
-                (first_box_pos_row + dr, first_box_pos_col + dc);
+    /// How the current level is going.
+    pub fn stats(&self) -> Stats {
+        let boxes_on_target = self
+            .state
+            .box_positions
+            .iter()
+            .filter(|pos| self.state.target_positions.contains(pos))
+            .count();
+        let record = self.progress.get(&self.initial_state);
+
+        Stats {
+            level: self.level_index + 1,
+            name: self.level().name(),
+            moves: self.history.move_count(),
+            pushes: self.history.push_count(),
+            boxes_on_target,
+            boxes: self.state.box_positions.len(),
+            elapsed: self.elapsed,
+            best: record.and_then(|record| record.best()),
+            best_time: record.and_then(|record| record.best_time()),
+            solved: self.state.is_solved(),
+            deadlock: self.deadlock.clone(),
+        }
+    }
 
-            let mut player_route = self.find_route_to_target(
-                self.player_position,
-                (player_target_row, player_target_col),
-                player_condition_fn,
-            );
+    /// The moves made on the current level, including the redo branch.
+    pub fn history(&self) -> &History {
+        &self.history
+    }
 
-            player_route.push((first_box_pos_row, first_box_pos_col));
+    /// Restarts the level and queues `lurd` as the redo branch, so the
+    /// solution can be stepped through with redo.
+    pub fn load_solution(&mut self, lurd: &str) -> Result<(), ParseLurdError> {
+        self.history = History::from_lurd(lurd)?;
+        self.state = self.initial_state.clone();
+        self.replaying = true;
+        self.front_end.game_event(&GameEvent::Restarted);
+        self.state_changed();
+        Ok(())
+    }
 
-            self.player_route = player_route;
+    /// Runs the game until the front end reports `Quit`.
+    ///
+    /// Each turn of the loop draws the state if needed, then waits for a
+    /// front end event no longer than until the next tick or autoplay step.
+    pub fn run(&mut self) {
+        self.level_loaded();
+        if self.start_in_menu {
+            self.show_levels();
         }
-    }
 
-    fn find_route_to_target(
-        &self,
-        start: (i32, i32),
-        target: (i32, i32),
-        condition_fn: impl Fn(&GameState, (&i32, &i32), (&i32, &i32), (&i32, &i32)) -> bool,
-    ) -> Vec<(i32, i32)> {
-        let mut route = Vec::new();
-        let mut visited = HashSet::<(i32, i32)>::new();
-        let (map_rows, map_cols) = self.map_size;
-        let mut queue = VecDeque::<(i32, i32)>::new();
-        let mut parent_map = HashMap::<(i32, i32), (i32, i32)>::new();
-
-        queue.push_back(start);
-
-        while let Some((current_row, current_col)) = queue.pop_front() {
-            visited.insert((current_row, current_col));
-
-            if (current_row, current_col) == target {
-                break;
+        let mut dirty = true;
+        let mut last_tick = Instant::now();
+        let mut next_step = last_tick + self.autoplay_delay;
+
+        loop {
+            if dirty || self.front_end.needs_redraw() {
+                self.front_end.render(&self.state, &self.stats());
+                dirty = false;
             }
 
-            for (dr, dc) in &[(0, 1), (1, 0), (0, -1), (-1, 0)] {
-                let (next_row, next_col) = (current_row + dr, current_col + dc);
-
-                if next_row < 0
-                    || next_row >= map_rows
-                    || next_col < 0
-                    || next_col >= map_cols
-                    || condition_fn(
-                        self,
-                        (&map_rows, &map_cols),
-                        (&current_row, &current_col),
-                        (dr, dc),
-                    )
-                    || visited.contains(&(next_row, next_col))
-                {
-                    continue; // Out of bounds or blocked by wall
-                }
+            let mut deadline = last_tick + TICK;
+            if self.mode == Mode::Autoplay && self.solving.is_none() {
+                deadline = deadline.min(next_step);
+            }
+            let timeout = deadline.saturating_duration_since(Instant::now());
+
+            // While the solver works, wait on it rather than on the front end,
+            // so its answer is used as soon as it is ready
+            let event = match self.solving {
+                Some(_) => self.front_end.poll_event(Duration::ZERO),
+                None => self.front_end.poll_event(timeout),
+            };
+            let idle = event.is_none();
+            match event {
+                Some(FrontEndEvent::Input(InputEvent::Quit)) => break,
+                Some(FrontEndEvent::Input(input)) => dirty |= self.handle_input(input),
+                Some(FrontEndEvent::Resize { .. }) => dirty = true,
+                None => {}
+            }
+            if idle
+                && let Some((search, solving)) = &self.solving
+                && let Some(result) = solving.wait(timeout)
+            {
+                let search = *search;
+                self.solving = None;
+                self.search_done(search, result);
+            }
 
-                queue.push_back((next_row, next_col));
-                parent_map.insert((next_row, next_col), (current_row, current_col));
+            let now = Instant::now();
+            if now >= last_tick + TICK {
+                if !self.state.is_solved() {
+                    let seconds = self.elapsed.as_secs();
+                    self.elapsed += now - last_tick;
+                    dirty |= self.elapsed.as_secs() != seconds; // For the clock
+                }
+                self.front_end.game_event(&GameEvent::Tick(now - last_tick));
+                last_tick = now;
+            }
+            if self.mode == Mode::Autoplay && now >= next_step {
+                dirty |= self.autoplay_step();
+                next_step = now + self.autoplay_delay;
             }
         }
+    }
 
-        let mut now_target = (target.0, target.1);
+    /// Applies one input. Returns whether the state changed.
+    fn handle_input(&mut self, input: InputEvent) -> bool {
+        if self.mode == Mode::Autoplay {
+            return false; // Only quitting is allowed while the solver plays
+        }
 
-        while let Some((current_row, current_col)) = parent_map.get(&now_target) {
-            route.push((*current_row, *current_col));
-            if (*current_row, *current_col) == start {
-                break; // Reached the start position
+        match input {
+            InputEvent::Undo => {
+                let Some(&mv) = self.history.moves().last() else {
+                    return false;
+                };
+                self.history.undo(&mut self.state);
+                self.front_end.game_event(&GameEvent::Undone(mv));
+            }
+            InputEvent::Redo => {
+                let Some(&mv) = self.history.redo_moves().first() else {
+                    return false;
+                };
+                if !self.history.redo(&mut self.state) {
+                    return false;
+                }
+                self.front_end.game_event(&GameEvent::Moved(mv));
+            }
+            InputEvent::Restart => {
+                self.state = self.initial_state.clone();
+                self.history.clear();
+                self.replaying = false;
+                self.front_end.game_event(&GameEvent::Restarted);
+            }
+            InputEvent::NextLevel => {
+                return self.state.is_solved() && self.next_level();
+            }
+            InputEvent::Menu => {
+                self.show_levels();
+                return false;
+            }
+            InputEvent::GoToLevel(number) => return self.go_to_level(number),
+            InputEvent::Hint => {
+                if !self.state.is_solved() {
+                    let search = self.hint_solver.solve_in_background(&self.state);
+                    self.solving = Some((Search::Hint, search));
+                }
+                return false;
+            }
+            InputEvent::WalkTo(target) => {
+                let route = self.state.walk_route(target);
+                return route.is_some_and(|route| self.make_moves(route));
+            }
+            InputEvent::PushBox { from, to } => {
+                let route = if self.state.box_positions.contains(&from) {
+                    self.state.push_route(from, to)
+                } else {
+                    self.state.walk_route(to) // Dragged from an empty cell
+                };
+                return route.is_some_and(|route| self.make_moves(route));
+            }
+            _ => {
+                let Some(direction) = input.direction() else {
+                    return false;
+                };
+                return self.make_move(direction);
             }
-            now_target = (*current_row, *current_col);
         }
 
-        route.reverse(); // Reverse the route to get it from start to target
+        self.state_changed();
+        true
+    }
 
-        if !route.is_empty() {
-            route.push(target); // Ensure the target is included in the route
+    /// Moves the player and tells the front end. Returns whether the move
+    /// was legal.
+    fn make_move(&mut self, direction: (i32, i32)) -> bool {
+        let result = self.history.play(&mut self.state, direction);
+        if result == MoveResult::Blocked {
+            return false;
         }
 
-        route
+        self.front_end.game_event(&GameEvent::Moved(Move {
+            direction,
+            push: result == MoveResult::Pushed,
+        }));
+        self.state_changed();
+        true
+    }
+
+    /// Makes each move in turn. Returns whether any of them was legal.
+    fn make_moves(&mut self, directions: Vec<(i32, i32)>) -> bool {
+        let mut moved = false;
+        for direction in directions {
+            moved |= self.make_move(direction);
+        }
+        moved
     }
 
-    fn check_valid(&self) -> bool {
-        if self.box_route.is_empty() {
-            return false; // No route found
+    /// Makes the next move of the solver's solution, and moves on to the next
+    /// level once the current one is solved. Without a solution yet, starts
+    /// the solver in the background instead. Returns whether the state
+    /// changed.
+    fn autoplay_step(&mut self) -> bool {
+        if self.state.is_solved() {
+            return self.next_level();
         }
 
-        true
+        let Some(plan) = &mut self.plan else {
+            if self.solving.is_none() {
+                let search = solver::Solver::new().solve_in_background(&self.state);
+                self.solving = Some((Search::Autoplay, search));
+            }
+            return false;
+        };
+
+        match plan.pop_front() {
+            Some(direction) => self.make_move(direction),
+            None => false,
+        }
     }
 
-    pub fn is_deadlock(&self) -> bool {
-        // Check if the player is in a deadlock position
-        for pos in &self.box_positions {
-            if self.dead_pos.contains(pos) {
-                return true; // Found a deadlock position
+    /// Hands the result of a background search to whoever asked for it.
+    fn search_done(
+        &mut self,
+        search: Search,
+        result: Result<solver::Solution, solver::SolveError>,
+    ) {
+        match search {
+            Search::Autoplay => self.plan = Some(self.plan_moves(result)),
+            Search::Hint => {
+                let hint = result.map(|solution| hint::Hint::from_solution(&solution));
+                if let Some(hint) = hint.transpose() {
+                    self.front_end.game_event(&GameEvent::Hint(hint));
+                }
             }
         }
+    }
 
-        false
+    /// The moves of a solution of the current state, none if there is no
+    /// solution to play.
+    fn plan_moves(
+        &self,
+        result: Result<solver::Solution, solver::SolveError>,
+    ) -> VecDeque<(i32, i32)> {
+        let Ok(solution) = result else {
+            return VecDeque::new(); // Nothing to play, stay on this level
+        };
+        let lurd = solution.to_lurd(&self.state);
+        history::parse_lurd(&lurd)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|mv| mv.direction)
+            .collect()
     }
 
-    pub fn is_solved(&self) -> bool {
-        // Check if all boxes are on target positions
-        self.box_positions
-            .iter()
-            .all(|pos| self.target_positions.contains(pos))
+    fn show_levels(&mut self) {
+        let event = GameEvent::ShowLevels {
+            levels: self.level_infos(),
+            current: self.level_index + 1,
+        };
+        self.front_end.game_event(&event);
     }
-}
 
-pub struct Game<F: FrontEnd> {
-    pub state: GameState,
-    pub front_end: F,
-    prev_states: Vec<GameState>,
-    after_states: Vec<GameState>,
-}
+    fn level_loaded(&mut self) {
+        self.plan = None;
+        self.solving = None;
+        self.deadlock = None;
+        let event = GameEvent::LevelLoaded {
+            number: self.level_index + 1,
+            name: self.level().name(),
+        };
+        self.front_end.game_event(&event);
+        self.state_changed();
+    }
 
-impl<F: FrontEnd> Game<F> {
-    pub fn new(state: GameState) -> Self {
-        Game {
-            state,
-            front_end: F::default(),
-            prev_states: Vec::new(),
-            after_states: Vec::new(),
+    /// Reports a newly solved or deadlocked position. A solution the player
+    /// found is also recorded in `progress`, one made by autoplay or replayed
+    /// from `load_solution` is not.
+    fn state_changed(&mut self) {
+        // A hint still being searched for would be for the old position
+        if matches!(self.solving, Some((Search::Hint, _))) {
+            self.solving = None;
         }
-    }
 
-    pub fn run(&mut self) {
-        loop {
-            if !self.state.is_solved() {
-                self.state.generate_route();
-                for pos in &self.state.player_route {
-                    self.state
-                        .box_positions
-                        .iter_mut()
-                        .find(|box_pos| **box_pos == *pos)
-                        .map(|box_pos| {
-                            let (dr, dc) = (
-                                pos.0 - self.state.player_position.0,
-                                pos.1 - self.state.player_position.1,
-                            );
-                            box_pos.0 += dr;
-                            box_pos.1 += dc;
-                        });
-                    self.state.player_position = *pos;
-                    self.front_end.render(&self.state);
-                    thread::sleep(std::time::Duration::from_millis(100));
-                }
+        if self.state.is_solved() {
+            self.deadlock = None;
+            let completion = Completion {
+                name: self.level().name(),
+                moves: self.history.move_count(),
+                pushes: self.history.push_count(),
+                time: self.elapsed,
+                lurd: self.history.to_lurd(),
+            };
+            let by_player = self.mode == Mode::Interactive && !self.replaying;
+            if by_player && self.progress.record(&self.initial_state, completion) {
+                // A failed save is retried with the next record, and the
+                // caller can save again and report it when the game ends
+                let _ = self.progress.save();
             }
-            // if let Some(event) = self.front_end.get_input() {
-            //     let (mut player_row, mut player_col) = self.state.player_position;
-
-            //     match event {
-            //         input::InputEvent::MoveUp => {
-            //             player_row -= 1;
-            //         }
-            //         input::InputEvent::MoveDown => {
-            //             player_row += 1;
-            //         }
-            //         input::InputEvent::MoveLeft => {
-            //             player_col -= 1;
-            //         }
-            //         input::InputEvent::MoveRight => {
-            //             player_col += 1;
-            //         }
-            //         input::InputEvent::Undo => {
-            //             // Implement undo logic
-            //             if let Some(last_state) = self.prev_states.pop() {
-            //                 self.after_states.push(self.state.clone());
-            //                 self.state = last_state;
-            //             }
-            //             continue;
-            //         }
-            //         input::InputEvent::Redo => {
-            //             // Implement redo logic
-            //             if let Some(next_state) = self.after_states.pop() {
-            //                 self.prev_states.push(self.state.clone());
-            //                 self.state = next_state;
-            //             }
-            //             continue;
-            //         }
-            //         input::InputEvent::Restart => {
-            //             // Reset the game state to the initial state
-            //             if let Some(initial_state) = self.prev_states.first() {
-            //                 self.state = initial_state.clone();
-            //                 self.prev_states.clear();
-            //                 self.after_states.clear();
-            //             }
-            //             continue;
-            //         }
-            //         input::InputEvent::Quit => {
-            //             break; // Exit the game loop
-            //         }
-            //     }
-            //     // Check if the new position is valid
-            //     if player_row >= 0
-            //         && player_row < self.state.map_size.0
-            //         && player_col >= 0
-            //         && player_col < self.state.map_size.1
-            //         && !self.state.walls.contains(&(player_row, player_col))
-            //     {
-            //         // check if the new position has a box
-            //         if let Some(box_index) = self
-            //             .state
-            //             .box_positions
-            //             .iter()
-            //             .position(|&pos| pos == (player_row, player_col))
-            //         {
-            //             // if it has a box, check if the next position is valid
-            //             let next_row = player_row + (player_row - self.state.player_position.0);
-            //             let next_col = player_col + (player_col - self.state.player_position.1);
-
-            //             if next_row >= 0
-            //                 && next_row < self.state.map_size.0
-            //                 && next_col >= 0
-            //                 && next_col < self.state.map_size.1
-            //                 && !self.state.walls.contains(&(next_row, next_col))
-            //                 && !self.state.box_positions.contains(&(next_row, next_col))
-            //             {
-            //                 self.prev_states.push(self.state.clone());
-            //                 self.after_states.clear();
-            //                 // move the box
-            //                 self.state.box_positions[box_index] = (next_row, next_col);
-            //                 self.state.player_position = (player_row, player_col);
-
-            //                 self.state.generate_route();
-            //             } else {
-            //                 continue; // Invalid move, skip updating player position
-            //             }
-            //         } else {
-            //             self.prev_states.push(self.state.clone());
-            //             self.after_states.clear();
-            //             // Just move the player
-            //             self.state.player_position = (player_row, player_col);
-            //             self.state.generate_route();
-            //         }
-            //     }
-            // }
+            self.front_end.game_event(&GameEvent::Solved);
+            return;
+        }
+
+        let deadlock = self.state.deadlock_reason();
+        if deadlock.is_some() && deadlock != self.deadlock {
+            let event = GameEvent::Deadlocked(deadlock.clone().expect("checked above"));
+            self.front_end.game_event(&event);
         }
+        self.deadlock = deadlock;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game::testing::{CORRIDOR, mission, xsb},
+        rendering::headless::HeadlessFrontEnd,
+    };
+
+    fn headless(
+        state: GameState,
+        mode: Mode,
+        front_end: HeadlessFrontEnd,
+    ) -> Game<HeadlessFrontEnd> {
+        let mut game = Game::new(state, mode);
+        game.front_end = front_end;
+        game.autoplay_delay = Duration::ZERO;
+        game
+    }
+
+    #[test]
+    fn autoplay_solves_the_missions() {
+        for name in ["mission1.txt", "mission3.txt"] {
+            let front_end = HeadlessFrontEnd::new([]).with_idle_limit(1_000);
+            let mut game = headless(mission(name), Mode::Autoplay, front_end);
+            game.run();
+
+            assert!(game.state.is_solved(), "{name}");
+            assert!(
+                game.front_end.events().contains(&GameEvent::Solved),
+                "{name}"
+            );
+            // The solver's solution is not the player's record
+            assert!(game.progress.get(&game.initial_state).is_none(), "{name}");
+        }
+    }
+
+    #[test]
+    fn autoplay_quits_while_the_solver_is_busy() {
+        // XSokoban level 1, which takes the solver many seconds
+        let state = xsb(concat!(
+            "    #####\n",
+            "    #   #\n",
+            "    #$  #\n",
+            "  ###  $##\n",
+            "  #  $ $ #\n",
+            "### # ## #   ######\n",
+            "#   # ## #####  ..#\n",
+            "# $  $          ..#\n",
+            "##### ### #@##  ..#\n",
+            "    #     #########\n",
+            "    #######",
+        ));
+        let front_end = HeadlessFrontEnd::new([]).with_idle_limit(2);
+        let mut game = headless(state, Mode::Autoplay, front_end);
+
+        let start = Instant::now();
+        game.run();
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(game.solving.is_some());
+    }
+
+    #[test]
+    fn a_hint_arrives_from_the_background() {
+        let front_end = HeadlessFrontEnd::new([InputEvent::Hint]).with_idle_limit(1_000);
+        let mut game = headless(mission("mission3.txt"), Mode::Interactive, front_end);
+        game.run();
+
+        let hint = game
+            .front_end
+            .events()
+            .iter()
+            .find_map(|event| match event {
+                GameEvent::Hint(hint) => Some(hint.clone()),
+                _ => None,
+            });
+        let hint = hint.expect("a hint event").expect("a hint");
+        assert!(game.state.box_positions.contains(&hint.push.box_position));
+        assert!(game.solving.is_none());
+    }
+
+    #[test]
+    fn scripted_moves_undo_and_redo_are_rendered() {
+        let state = xsb("#######\n#@ $ .#\n#######");
+        let script = [
+            InputEvent::MoveRight,
+            InputEvent::MoveRight,
+            InputEvent::Undo,
+            InputEvent::Redo,
+            InputEvent::MoveRight,
+        ];
+        let mut game = headless(state, Mode::Interactive, HeadlessFrontEnd::new(script));
+        game.run();
+
+        let rows: Vec<&str> = game
+            .front_end
+            .frames()
+            .iter()
+            .map(|frame| frame.lines().nth(1).unwrap())
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                "#@ $ .#", "# @$ .#", "#  @$.#", "# @$ .#", "#  @$.#", "#   @*#"
+            ]
+        );
+        assert!(game.state.is_solved());
+        assert_eq!(game.history().to_lurd(), "rRR");
+
+        let walk = Move::from_char('r').unwrap();
+        let push = Move::from_char('R').unwrap();
+        let events: Vec<&GameEvent> = game
+            .front_end
+            .events()
+            .iter()
+            .filter(|event| !matches!(event, GameEvent::Tick(_)))
+            .collect();
+        assert_eq!(
+            events[1..],
+            [
+                &GameEvent::Moved(walk),
+                &GameEvent::Moved(push),
+                &GameEvent::Undone(push),
+                &GameEvent::Moved(push),
+                &GameEvent::Moved(push),
+                &GameEvent::Solved,
+            ]
+        );
+    }
+
+    #[test]
+    fn a_loaded_solution_replays_without_a_record() {
+        let state = xsb(CORRIDOR);
+        let mut game = headless(
+            state,
+            Mode::Interactive,
+            HeadlessFrontEnd::new([InputEvent::Redo]),
+        );
+        game.load_solution("R").unwrap();
+        game.run();
+
+        assert!(game.state.is_solved());
+        assert!(game.progress.get(&game.initial_state).is_none());
+    }
+    #[test]
+    fn a_solution_found_by_the_player_is_recorded() {
+        let state = xsb(CORRIDOR);
+        let front_end = HeadlessFrontEnd::new([InputEvent::MoveRight]);
+        let mut game = headless(state, Mode::Interactive, front_end);
+        game.run();
+
+        let record = game.progress.get(&game.initial_state).unwrap();
+        assert_eq!(record.solution.as_deref(), Some("R"));
+    }
+
+    #[test]
+    fn a_session_resumes_with_its_redo_branch() {
+        let state = xsb("#######\n#@ $ .#\n#######");
+        let script = [
+            InputEvent::MoveRight,
+            InputEvent::MoveRight,
+            InputEvent::Undo,
+        ];
+        let mut game = headless(
+            state.clone(),
+            Mode::Interactive,
+            HeadlessFrontEnd::new(script),
+        );
+        game.run();
+        let session = game.to_session(PathBuf::from("level.xsb"));
+
+        let mut resumed = headless(state, Mode::Interactive, HeadlessFrontEnd::default());
+        resumed.resume(&session).unwrap();
+        assert_eq!(resumed.state, game.state);
+        assert_eq!(resumed.history().redo_moves(), game.history().redo_moves());
+
+        let mut tampered = session.clone();
+        tampered.cursor = 0;
+        assert!(matches!(
+            resumed.resume(&tampered),
+            Err(SessionError::Mismatch)
+        ));
     }
 }
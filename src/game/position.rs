@@ -0,0 +1,400 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::{BuildHasherDefault, Hash, Hasher},
+};
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::game::GameState;
+
+pub const DIRECTIONS: [(i32, i32); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+/// Seed for the Zobrist keys, fixed so hashes are stable between runs.
+const ZOBRIST_SEED: u64 = 0x50b0_ba17_2024_0001;
+
+/// The static part of a level: which cells are floor and which are targets,
+/// plus the Zobrist keys used to hash positions on it.
+#[derive(Debug, Clone)]
+pub struct Board {
+    rows: i32,
+    cols: i32,
+    floor: Vec<bool>,
+    target: Vec<bool>,
+    box_keys: Vec<u64>,
+    player_keys: Vec<u64>,
+}
+
+impl Board {
+    pub fn new(state: &GameState) -> Self {
+        let (rows, cols) = state.map_size;
+        let size = (rows * cols).max(0) as usize;
+        let mut floor = vec![true; size];
+        let mut target = vec![false; size];
+
+        for &(r, c) in &state.walls {
+            if r >= 0 && r < rows && c >= 0 && c < cols {
+                floor[(r * cols + c) as usize] = false;
+            }
+        }
+        for &(r, c) in &state.target_positions {
+            if r >= 0 && r < rows && c >= 0 && c < cols {
+                target[(r * cols + c) as usize] = true;
+            }
+        }
+
+        let mut rng = StdRng::seed_from_u64(ZOBRIST_SEED);
+        let box_keys = (0..size).map(|_| rng.random()).collect();
+        let player_keys = (0..size).map(|_| rng.random()).collect();
+
+        Board {
+            rows,
+            cols,
+            floor,
+            target,
+            box_keys,
+            player_keys,
+        }
+    }
+
+    /// Number of cells, including walls.
+    pub fn len(&self) -> usize {
+        self.floor.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.floor.is_empty()
+    }
+
+    pub fn index(&self, (r, c): (i32, i32)) -> Option<usize> {
+        if r < 0 || r >= self.rows || c < 0 || c >= self.cols {
+            return None;
+        }
+        Some((r * self.cols + c) as usize)
+    }
+
+    pub fn position(&self, index: usize) -> (i32, i32) {
+        (index as i32 / self.cols, index as i32 % self.cols)
+    }
+
+    pub fn is_floor(&self, index: usize) -> bool {
+        self.floor[index]
+    }
+
+    pub fn is_target(&self, index: usize) -> bool {
+        self.target[index]
+    }
+
+    /// The floor cell next to `index` in `direction`, if there is one.
+    pub fn neighbor(&self, index: usize, (dr, dc): (i32, i32)) -> Option<usize> {
+        let (r, c) = self.position(index);
+        self.index((r + dr, c + dc))
+            .filter(|&next| self.floor[next])
+    }
+
+    /// Every cell the player can walk to from `start` without pushing a box.
+    pub fn reachable(&self, boxes: &BitSet, start: usize) -> BitSet {
+        let mut visited = BitSet::new(self.len());
+        let mut queue = VecDeque::from([start]);
+        visited.insert(start);
+
+        while let Some(index) = queue.pop_front() {
+            for direction in DIRECTIONS {
+                if let Some(next) = self.neighbor(index, direction)
+                    && !visited.contains(next)
+                    && !boxes.contains(next)
+                {
+                    visited.insert(next);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        visited
+    }
+
+    /// Minimum number of pushes needed to bring a lone box from each cell onto
+    /// any of `goals`. Boxes are pulled backwards from the goals: a box can be
+    /// pulled from `cell` to `cell + d` only if the player has room at
+    /// `cell + 2d`.
+    pub fn pull_distances(&self, goals: impl IntoIterator<Item = usize>) -> Vec<Option<u32>> {
+        let mut distance = vec![None; self.len()];
+        let mut queue = VecDeque::new();
+        for goal in goals {
+            if self.floor[goal] && distance[goal].is_none() {
+                distance[goal] = Some(0);
+                queue.push_back(goal);
+            }
+        }
+
+        while let Some(index) = queue.pop_front() {
+            let current = distance[index].unwrap_or(0);
+            for direction in DIRECTIONS {
+                if let Some(box_prev) = self.neighbor(index, direction)
+                    && self.neighbor(box_prev, direction).is_some()
+                    && distance[box_prev].is_none()
+                {
+                    distance[box_prev] = Some(current + 1);
+                    queue.push_back(box_prev);
+                }
+            }
+        }
+
+        distance
+    }
+
+    /// Shortest walk from `start` to `goal` that does not touch any box,
+    /// excluding `start` itself.
+    pub fn walk(&self, boxes: &BitSet, start: usize, goal: usize) -> Option<Vec<usize>> {
+        let mut parent = vec![usize::MAX; self.len()];
+        let mut queue = VecDeque::from([start]);
+        parent[start] = start;
+
+        while let Some(index) = queue.pop_front() {
+            if index == goal {
+                let mut path = Vec::new();
+                let mut current = goal;
+                while current != start {
+                    path.push(current);
+                    current = parent[current];
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for direction in DIRECTIONS {
+                if let Some(next) = self.neighbor(index, direction)
+                    && parent[next] == usize::MAX
+                    && !boxes.contains(next)
+                {
+                    parent[next] = index;
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Fixed-size set of cell indices.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    pub fn new(len: usize) -> Self {
+        BitSet {
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        self.words[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    pub fn insert(&mut self, index: usize) {
+        self.words[index / 64] |= 1 << (index % 64);
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        self.words[index / 64] &= !(1 << (index % 64));
+    }
+
+    pub fn count(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// The smallest index in the set.
+    pub fn first(&self) -> Option<usize> {
+        self.words
+            .iter()
+            .position(|&word| word != 0)
+            .map(|i| i * 64 + self.words[i].trailing_zeros() as usize)
+    }
+
+    /// Indices in the set, in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| i * 64 + bit)
+        })
+    }
+}
+
+/// A compact, hashable snapshot of the dynamic part of a `GameState`.
+///
+/// Two positions are equal when they have the same boxes and the player can
+/// reach the same region, regardless of where exactly the player stands. The
+/// region is identified by its top-left-most cell.
+#[derive(Debug, Clone)]
+pub struct Position {
+    boxes: BitSet,
+    player: usize,
+    region: usize,
+    hash: u64,
+}
+
+impl Position {
+    /// Builds a position from box cells and the player cell. Returns `None` if
+    /// any of them is not a floor cell of `board`.
+    pub fn new(board: &Board, boxes: &[(i32, i32)], player: (i32, i32)) -> Option<Self> {
+        let player = board.index(player).filter(|&i| board.is_floor(i))?;
+        let mut set = BitSet::new(board.len());
+        let mut hash = 0;
+
+        for &pos in boxes {
+            let index = board.index(pos).filter(|&i| board.is_floor(i))?;
+            if !set.contains(index) {
+                set.insert(index);
+                hash ^= board.box_keys[index];
+            }
+        }
+
+        let mut position = Position {
+            boxes: set,
+            player,
+            region: player,
+            hash,
+        };
+        position.region = position.compute_region(board);
+        position.hash ^= board.player_keys[position.region];
+        Some(position)
+    }
+
+    pub fn boxes(&self) -> &BitSet {
+        &self.boxes
+    }
+
+    /// The cell the player actually stands on.
+    pub fn player(&self) -> usize {
+        self.player
+    }
+
+    /// The normalized player key: the top-left-most cell the player can reach.
+    pub fn region(&self) -> usize {
+        self.region
+    }
+
+    pub fn zobrist(&self) -> u64 {
+        self.hash
+    }
+
+    pub fn is_solved(&self, board: &Board) -> bool {
+        self.boxes.iter().all(|index| board.is_target(index))
+    }
+
+    /// Pushes the box at `box_index` onto the adjacent cell `dest` and moves
+    /// the player onto the cell it left. The caller is responsible for checking
+    /// that the push is legal.
+    pub fn push(&mut self, board: &Board, box_index: usize, dest: usize) {
+        self.boxes.remove(box_index);
+        self.boxes.insert(dest);
+        self.hash ^= board.box_keys[box_index] ^ board.box_keys[dest];
+        self.hash ^= board.player_keys[self.region];
+        self.player = box_index;
+        self.region = self.compute_region(board);
+        self.hash ^= board.player_keys[self.region];
+    }
+
+    fn compute_region(&self, board: &Board) -> usize {
+        board
+            .reachable(&self.boxes, self.player)
+            .first()
+            .unwrap_or(self.player)
+    }
+}
+
+impl PartialEq for Position {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash && self.region == other.region && self.boxes == other.boxes
+    }
+}
+
+impl Eq for Position {}
+
+impl Hash for Position {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+/// Passes the precomputed Zobrist hash straight through instead of hashing it
+/// a second time.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ZobristHasher(u64);
+
+impl Hasher for ZobristHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = self.0.rotate_left(8) ^ byte as u64;
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.0 ^= value;
+    }
+}
+
+pub type PositionMap<V> = HashMap<Position, V, BuildHasherDefault<ZobristHasher>>;
+
+impl GameState {
+    /// Converts the boxes and player into a `Position` on `board`.
+    pub fn to_position(&self, board: &Board) -> Option<Position> {
+        Position::new(board, &self.box_positions, self.player_position)
+    }
+
+    /// Moves the player and boxes to match `position`.
+    pub fn set_position(&mut self, board: &Board, position: &Position) {
+        self.player_position = board.position(position.player());
+        self.box_positions = position
+            .boxes()
+            .iter()
+            .map(|index| board.position(index))
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::testing::xsb;
+
+    const LEVEL: &str = "#######\n#@ $ .#\n# $. ##\n#######";
+
+    #[test]
+    fn a_position_converts_back_to_the_same_state() {
+        let state = xsb(LEVEL);
+        let board = Board::new(&state);
+        let position = state.to_position(&board).unwrap();
+
+        let mut other = xsb("#######\n# @  .#\n#$$. ##\n#######");
+        other.set_position(&board, &position);
+        assert_eq!(other.player_position, state.player_position);
+        assert_eq!(other.box_positions, [(1, 3), (2, 2)]);
+        assert_eq!(other.to_position(&board), Some(position));
+    }
+
+    #[test]
+    fn positions_differ_only_by_boxes_and_player_region() {
+        let board = Board::new(&xsb(LEVEL));
+        let start = xsb(LEVEL).to_position(&board).unwrap();
+        let walked = xsb("#######\n#  $ .#\n#@$. ##\n#######")
+            .to_position(&board)
+            .unwrap();
+        let pushed = xsb("#######\n#  @$.#\n# $. ##\n#######")
+            .to_position(&board)
+            .unwrap();
+
+        assert_eq!(walked, start);
+        assert_eq!(walked.zobrist(), start.zobrist());
+        assert_ne!(pushed, start);
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::game::{
+    GameState,
+    position::{BitSet, Board, DIRECTIONS},
+};
+
+/// Where the moved box is and where the player stands right after a push.
+type PushState = ((i32, i32), (i32, i32));
+
+/// What a breadth-first search over the pushes of one box found.
+struct PushSearch {
+    /// Every state reached, with the state and push it was reached by.
+    parents: HashMap<PushState, (PushState, (i32, i32))>,
+    visited: HashSet<PushState>,
+    /// The first state with the box on the goal, if there was a goal.
+    found: Option<PushState>,
+}
+
+impl GameState {
+    /// The moves that walk the player to `target` along a shortest path
+    /// without pushing anything. Empty if the player is already there,
+    /// `None` if it cannot get there.
+    pub fn walk_route(&self, target: (i32, i32)) -> Option<Vec<(i32, i32)>> {
+        if target == self.player_position {
+            return Some(Vec::new());
+        }
+
+        let board = Board::new(self);
+        let start = board.index(self.player_position)?;
+        let goal = board.index(target).filter(|&goal| board.is_floor(goal))?;
+        let path = board.walk(&self.box_set(&board, None), start, goal)?;
+
+        let mut from = self.player_position;
+        Some(
+            path.into_iter()
+                .map(|index| {
+                    let (row, col) = board.position(index);
+                    let direction = (row - from.0, col - from.1);
+                    from = (row, col);
+                    direction
+                })
+                .collect(),
+        )
+    }
+
+    /// Every cell the player can walk to without pushing anything, its own
+    /// cell included.
+    pub fn reachable_cells(&self) -> HashSet<(i32, i32)> {
+        let board = Board::new(self);
+        let Some(start) = board.index(self.player_position) else {
+            return HashSet::from([self.player_position]);
+        };
+
+        board
+            .reachable(&self.box_set(&board, None), start)
+            .iter()
+            .map(|index| board.position(index))
+            .collect()
+    }
+
+    /// Every cell the box at `from` can be pushed to, following the same
+    /// rules as `push_route`. Empty if there is no box at `from`.
+    pub fn push_reachable(&self, from: (i32, i32)) -> HashSet<(i32, i32)> {
+        let Some(box_index) = self.box_positions.iter().position(|&pos| pos == from) else {
+            return HashSet::new();
+        };
+
+        self.push_search(box_index, |_| false)
+            .visited
+            .into_iter()
+            .map(|(box_pos, _)| box_pos)
+            .filter(|&box_pos| box_pos != from)
+            .collect()
+    }
+
+    /// The moves that bring the box at `from` to `to` with as few pushes as
+    /// possible, pushing no other box. Before every push the player has to
+    /// be able to walk to the cell behind the box, with the box already in
+    /// its new place. `None` if there is no box at `from` or no such route.
+    pub fn push_route(&self, from: (i32, i32), to: (i32, i32)) -> Option<Vec<(i32, i32)>> {
+        let box_index = self.box_positions.iter().position(|&pos| pos == from)?;
+        let PushSearch { parents, found, .. } = self.push_search(box_index, |pos| pos == to);
+
+        let mut pushes = Vec::new();
+        let mut current = found?;
+        while let Some(&(previous, direction)) = parents.get(&current) {
+            pushes.push(direction);
+            current = previous;
+        }
+        pushes.reverse();
+
+        // Walk behind the box before each push, replaying on a copy so every
+        // walk sees the box where the previous push left it
+        let mut state = self.clone();
+        let mut moves = Vec::new();
+        for (dr, dc) in pushes {
+            let (box_row, box_col) = state.box_positions[box_index];
+            for direction in state.walk_route((box_row - dr, box_col - dc))? {
+                state.try_move(direction);
+                moves.push(direction);
+            }
+            state.try_move((dr, dc));
+            moves.push((dr, dc));
+        }
+
+        Some(moves)
+    }
+
+    /// Searches the pushes of the box at `box_index` breadth first, stopping
+    /// once the box reaches a cell `goal` accepts, or after every state.
+    fn push_search(&self, box_index: usize, goal: impl Fn((i32, i32)) -> bool) -> PushSearch {
+        let start = (self.box_positions[box_index], self.player_position);
+        let mut parents: HashMap<PushState, (PushState, (i32, i32))> = HashMap::new();
+        let mut visited = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+        let mut found = None;
+
+        let board = Board::new(self);
+        let mut boxes = self.box_set(&board, Some(box_index));
+
+        while let Some((box_pos, player)) = queue.pop_front() {
+            if goal(box_pos) {
+                found = Some((box_pos, player));
+                break;
+            }
+            let (Some(box_cell), Some(player_cell)) = (board.index(box_pos), board.index(player))
+            else {
+                continue;
+            };
+
+            boxes.insert(box_cell);
+            let reachable = board.reachable(&boxes, player_cell);
+            boxes.remove(box_cell);
+
+            for (dr, dc) in DIRECTIONS {
+                let behind = board.index((box_pos.0 - dr, box_pos.1 - dc));
+                let dest = (box_pos.0 + dr, box_pos.1 + dc);
+                let dest_open = board
+                    .index(dest)
+                    .is_some_and(|cell| board.is_floor(cell) && !boxes.contains(cell));
+                if !dest_open {
+                    continue;
+                }
+                if !behind.is_some_and(|cell| reachable.contains(cell)) {
+                    continue; // The player cannot get behind the box
+                }
+
+                let next = (dest, box_pos);
+                if visited.insert(next) {
+                    parents.insert(next, ((box_pos, player), (dr, dc)));
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        PushSearch {
+            parents,
+            visited,
+            found,
+        }
+    }
+
+    /// The cells of the boxes on `board`, leaving out the box at `except`.
+    fn box_set(&self, board: &Board, except: Option<usize>) -> BitSet {
+        let mut boxes = BitSet::new(board.len());
+        for (index, &pos) in self.box_positions.iter().enumerate() {
+            if Some(index) != except
+                && let Some(cell) = board.index(pos)
+            {
+                boxes.insert(cell);
+            }
+        }
+        boxes
+    }
+}
@@ -0,0 +1,383 @@
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::game::{
+    GameState,
+    deadlock::DeadlockDetector,
+    history::History,
+    position::{Board, DIRECTIONS, Position, PositionMap},
+};
+
+/// A single box push: the box at `box_position` is moved one cell in `direction`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Push {
+    pub box_position: (i32, i32),
+    pub direction: (i32, i32),
+}
+
+#[derive(Debug, Clone)]
+pub struct Solution {
+    /// The pushes in the order they have to be performed.
+    pub pushes: Vec<Push>,
+    /// Every cell the player steps on, starting after the initial position.
+    pub steps: Vec<(i32, i32)>,
+    /// Whether the solution is guaranteed to use the minimum number of pushes.
+    pub push_optimal: bool,
+    /// Number of states expanded by the search.
+    pub explored: usize,
+}
+
+impl Solution {
+    /// The player's steps in LURD notation, starting from `state`.
+    pub fn to_lurd(&self, state: &GameState) -> String {
+        let mut state = state.clone();
+        let mut history = History::new();
+        for &(r, c) in &self.steps {
+            let (player_row, player_col) = state.player_position;
+            history.play(&mut state, (r - player_row, c - player_col));
+        }
+        history.to_lurd()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolveError {
+    /// The whole state space was searched without finding a solution.
+    Unsolvable,
+    /// The search gave up after expanding the given number of states.
+    NodeLimit(usize),
+    /// A search in the background was stopped before it finished.
+    Cancelled,
+}
+
+impl std::fmt::Display for SolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolveError::Unsolvable => write!(f, "the level has no solution"),
+            SolveError::NodeLimit(limit) => {
+                write!(f, "no solution found within {limit} explored states")
+            }
+            SolveError::Cancelled => write!(f, "the search was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for SolveError {}
+
+/// A* search over push states.
+///
+/// A state is the set of box cells plus the region the player can reach, so
+/// player walking never shows up in the search tree, only pushes do. With the
+/// default weight of 1 the heuristic is admissible and the returned solution
+/// is push-optimal.
+#[derive(Debug, Clone)]
+pub struct Solver {
+    node_limit: usize,
+    weight: u32,
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Solver {
+            node_limit: 1_000_000,
+            weight: 1,
+        }
+    }
+}
+
+/// Minimum number of pushes needed to bring a box from each cell to any
+/// target, ignoring the other boxes.
+struct Distances(Vec<Option<u32>>);
+
+impl Distances {
+    fn new(board: &Board) -> Self {
+        let targets = (0..board.len()).filter(|&index| board.is_target(index));
+        Distances(board.pull_distances(targets))
+    }
+
+    fn heuristic(&self, position: &Position) -> Option<u32> {
+        position.boxes().iter().map(|index| self.0[index]).sum()
+    }
+}
+
+struct Node {
+    position: Position,
+    parent: Option<usize>,
+    push: Option<Push>,
+    pushes: u32,
+}
+
+impl Solver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stops the search after expanding `node_limit` states.
+    pub fn with_node_limit(mut self, node_limit: usize) -> Self {
+        self.node_limit = node_limit;
+        self
+    }
+
+    /// Multiplies the heuristic by `weight`. Anything above 1 usually finds a
+    /// solution much faster, but it is no longer guaranteed to be push-optimal.
+    pub fn with_weight(mut self, weight: u32) -> Self {
+        self.weight = weight.max(1);
+        self
+    }
+
+    pub fn solve(&self, state: &GameState) -> Result<Solution, SolveError> {
+        self.solve_until(state, &AtomicBool::new(false))
+    }
+
+    /// Solves `state` on a worker thread, so the caller can carry on.
+    pub fn solve_in_background(&self, state: &GameState) -> BackgroundSolve {
+        let (sender, receiver) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let solver = self.clone();
+        let state = state.clone();
+        let worker_stop = Arc::clone(&stop);
+        thread::spawn(move || {
+            // The receiver is gone if the search was dropped meanwhile
+            let _ = sender.send(solver.solve_until(&state, &worker_stop));
+        });
+
+        BackgroundSolve { receiver, stop }
+    }
+
+    /// Like `solve`, but gives up with `Cancelled` as soon as `stop` is set.
+    fn solve_until(&self, state: &GameState, stop: &AtomicBool) -> Result<Solution, SolveError> {
+        let board = Board::new(state);
+        let distances = Distances::new(&board);
+        let detector = DeadlockDetector::new(&board, state.dead_squares(&board));
+
+        let Some(start) = state.to_position(&board) else {
+            return Err(SolveError::Unsolvable);
+        };
+        let Some(start_h) = distances.heuristic(&start) else {
+            return Err(SolveError::Unsolvable); // A box already sits on a dead square
+        };
+
+        let mut best = PositionMap::<u32>::default();
+        best.insert(start.clone(), 0);
+
+        let mut nodes = vec![Node {
+            position: start,
+            parent: None,
+            push: None,
+            pushes: 0,
+        }];
+
+        let mut open = BinaryHeap::new();
+        open.push(Reverse((self.weight * start_h, start_h, 0usize)));
+
+        let mut explored = 0;
+
+        while let Some(Reverse((_, h, node_index))) = open.pop() {
+            let node = &nodes[node_index];
+
+            if best
+                .get(&node.position)
+                .is_some_and(|&known| known < node.pushes)
+            {
+                continue; // A cheaper way to this state was found after queueing it
+            }
+
+            if h == 0 && node.position.is_solved(&board) {
+                return Ok(self.build_solution(&board, &nodes, node_index, explored));
+            }
+
+            explored += 1;
+            if explored > self.node_limit {
+                return Err(SolveError::NodeLimit(self.node_limit));
+            }
+            if stop.load(Ordering::Relaxed) {
+                return Err(SolveError::Cancelled);
+            }
+
+            let boxes = node.position.boxes();
+            let reachable = board.reachable(boxes, node.position.player());
+            let pushes = node.pushes + 1;
+
+            let mut children = Vec::new();
+            for box_index in boxes.iter() {
+                for direction in DIRECTIONS {
+                    let behind = board.neighbor(box_index, (-direction.0, -direction.1));
+                    let Some(dest) = board.neighbor(box_index, direction) else {
+                        continue;
+                    };
+                    if !behind.is_some_and(|behind| reachable.contains(behind))
+                        || boxes.contains(dest)
+                        || detector.is_dead(dest)
+                    {
+                        continue;
+                    }
+
+                    let mut child = node.position.clone();
+                    child.push(&board, box_index, dest);
+                    if detector.is_push_deadlock(&child, box_index, dest, &reachable) {
+                        continue;
+                    }
+                    children.push((
+                        child,
+                        Push {
+                            box_position: board.position(box_index),
+                            direction,
+                        },
+                    ));
+                }
+            }
+
+            for (position, push) in children {
+                if best.get(&position).is_some_and(|&known| known <= pushes) {
+                    continue;
+                }
+                let Some(h) = distances.heuristic(&position) else {
+                    continue;
+                };
+                best.insert(position.clone(), pushes);
+
+                nodes.push(Node {
+                    position,
+                    parent: Some(node_index),
+                    push: Some(push),
+                    pushes,
+                });
+                open.push(Reverse((pushes + self.weight * h, h, nodes.len() - 1)));
+            }
+        }
+
+        Err(SolveError::Unsolvable)
+    }
+
+    fn build_solution(
+        &self,
+        board: &Board,
+        nodes: &[Node],
+        goal: usize,
+        explored: usize,
+    ) -> Solution {
+        let mut pushes = Vec::new();
+        let mut current = Some(goal);
+        while let Some(index) = current {
+            if let Some(push) = nodes[index].push {
+                pushes.push(push);
+            }
+            current = nodes[index].parent;
+        }
+        pushes.reverse();
+
+        // Expand the pushes into the full walk of the player
+        let mut position = nodes[0].position.clone();
+        let mut steps = Vec::new();
+
+        for push in &pushes {
+            let (dr, dc) = push.direction;
+            let (r, c) = push.box_position;
+            let (Some(box_index), Some(dest), Some(behind)) = (
+                board.index((r, c)),
+                board.index((r + dr, c + dc)),
+                board.index((r - dr, c - dc)),
+            ) else {
+                break;
+            };
+            let Some(path) = board.walk(position.boxes(), position.player(), behind) else {
+                break;
+            };
+
+            steps.extend(path.into_iter().map(|index| board.position(index)));
+            steps.push((r, c));
+            position.push(board, box_index, dest);
+        }
+
+        Solution {
+            pushes,
+            steps,
+            push_optimal: self.weight == 1,
+            explored,
+        }
+    }
+}
+
+/// A search started by `Solver::solve_in_background`. Dropping it stops the
+/// search.
+#[derive(Debug)]
+pub struct BackgroundSolve {
+    receiver: mpsc::Receiver<Result<Solution, SolveError>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl BackgroundSolve {
+    /// Waits up to `timeout` for the search to finish. Returns its result
+    /// once, or `None` if it is still running.
+    pub fn wait(&self, timeout: Duration) -> Option<Result<Solution, SolveError>> {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(result) => Some(result),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => Some(Err(SolveError::Cancelled)),
+        }
+    }
+}
+
+impl Drop for BackgroundSolve {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{
+        testing::{mission, xsb},
+        verify::verify,
+    };
+
+    #[test]
+    fn finds_push_optimal_solutions() {
+        for (name, pushes) in [("mission1.txt", 22), ("mission3.txt", 17)] {
+            let state = mission(name);
+            let solution = Solver::new().solve(&state).unwrap();
+
+            assert!(solution.push_optimal);
+            assert_eq!(solution.pushes.len(), pushes, "{name}");
+            let verified = verify(&state, &solution.to_lurd(&state)).unwrap();
+            assert_eq!(verified.pushes, pushes, "{name}");
+        }
+    }
+
+    #[test]
+    fn reports_unsolvable_levels() {
+        // The box is stuck in the corner
+        let state = xsb("#####\n#$  #\n# @.#\n#####");
+        assert_eq!(
+            Solver::new().solve(&state).err(),
+            Some(SolveError::Unsolvable)
+        );
+    }
+
+    #[test]
+    fn gives_up_at_the_node_limit() {
+        let state = mission("mission3.txt");
+        assert_eq!(
+            Solver::new().with_node_limit(2).solve(&state).err(),
+            Some(SolveError::NodeLimit(2))
+        );
+    }
+
+    #[test]
+    fn solves_in_the_background() {
+        let state = mission("mission1.txt");
+        let search = Solver::new().solve_in_background(&state);
+        let solution = search.wait(Duration::from_secs(60)).unwrap().unwrap();
+        assert_eq!(solution.pushes.len(), 22);
+    }
+}
@@ -0,0 +1,158 @@
+use crate::game::{
+    GameState,
+    position::{BitSet, Board},
+};
+
+/// What a single cell of the map is, as far as boxes are concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SquareKind {
+    Wall,
+    /// Open space outside the walls of the level.
+    Void,
+    /// Floor inside the walls that the player can never walk to.
+    Unreachable,
+    /// Floor the player can reach, but a box standing on it can never be
+    /// pushed onto a target.
+    Dead,
+    /// Floor from which a box can still reach a target.
+    Live,
+}
+
+/// Per-cell classification of a level, computed once from its walls and
+/// targets. Cells are stored row by row, so `index = row * cols + col`
+/// matches the indices used by `position::Board`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SquareMap {
+    rows: i32,
+    cols: i32,
+    cells: Vec<SquareKind>,
+}
+
+impl SquareMap {
+    pub fn new(state: &GameState) -> Self {
+        let (rows, cols) = state.map_size;
+        let size = (rows * cols).max(0) as usize;
+        let mut map = SquareMap {
+            rows,
+            cols,
+            cells: vec![SquareKind::Unreachable; size],
+        };
+
+        for &pos in &state.walls {
+            if let Some(index) = map.index(pos) {
+                map.cells[index] = SquareKind::Wall;
+            }
+        }
+
+        // Everything the player can walk to if the boxes were not there
+        let board = Board::new(state);
+        let no_boxes = BitSet::new(size);
+        let reachable = match board.index(state.player_position) {
+            Some(start) if board.is_floor(start) => board.reachable(&no_boxes, start),
+            _ => BitSet::new(size),
+        };
+
+        // Open cells connected to the edge of the map that the player cannot
+        // reach lie outside the walls
+        let mut outside = BitSet::new(size);
+        for index in 0..size {
+            let (r, c) = map.position(index);
+            let on_edge = r == 0 || r == rows - 1 || c == 0 || c == cols - 1;
+            if on_edge
+                && board.is_floor(index)
+                && !reachable.contains(index)
+                && !outside.contains(index)
+            {
+                for cell in board.reachable(&no_boxes, index).iter() {
+                    outside.insert(cell);
+                }
+            }
+        }
+
+        for index in 0..size {
+            if map.cells[index] == SquareKind::Wall {
+                continue;
+            }
+            map.cells[index] = if reachable.contains(index) {
+                SquareKind::Dead
+            } else if outside.contains(index) {
+                SquareKind::Void
+            } else {
+                SquareKind::Unreachable
+            };
+        }
+
+        // Cells the player can reach from which a box can still be pushed
+        // onto a target
+        let targets = state
+            .target_positions
+            .iter()
+            .filter_map(|&pos| board.index(pos));
+        for (index, distance) in board.pull_distances(targets).into_iter().enumerate() {
+            if distance.is_some() && map.cells[index] == SquareKind::Dead {
+                map.cells[index] = SquareKind::Live;
+            }
+        }
+
+        map
+    }
+
+    /// The kind of the cell at `pos`. Anything outside the map is `Void`.
+    pub fn get(&self, pos: (i32, i32)) -> SquareKind {
+        self.index(pos)
+            .map_or(SquareKind::Void, |index| self.cells[index])
+    }
+
+    /// The kind of the cell with the given row-major index.
+    pub fn get_index(&self, index: usize) -> SquareKind {
+        self.cells.get(index).copied().unwrap_or(SquareKind::Void)
+    }
+
+    /// Whether a box on `pos` can still be brought to a target.
+    pub fn is_live(&self, pos: (i32, i32)) -> bool {
+        self.get(pos) == SquareKind::Live
+    }
+
+    /// Every position of the given kind, row by row.
+    pub fn positions(&self, kind: SquareKind) -> impl Iterator<Item = (i32, i32)> + '_ {
+        (0..self.cells.len())
+            .filter(move |&index| self.cells[index] == kind)
+            .map(|index| self.position(index))
+    }
+
+    fn index(&self, (r, c): (i32, i32)) -> Option<usize> {
+        if r < 0 || r >= self.rows || c < 0 || c >= self.cols {
+            return None;
+        }
+        Some((r * self.cols + c) as usize)
+    }
+
+    fn position(&self, index: usize) -> (i32, i32) {
+        (index as i32 / self.cols, index as i32 % self.cols)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::testing::xsb;
+
+    const LEVEL: &str = "  #######\n  #@ $ .#\n  #  #  #\n  #######\n  # #\n  ###";
+
+    #[test]
+    fn cells_are_classified_by_walls_targets_and_reach() {
+        let squares = SquareMap::new(&xsb(LEVEL));
+
+        assert_eq!(squares.get((1, 2)), SquareKind::Wall);
+        assert_eq!(squares.get((0, 0)), SquareKind::Void);
+        assert_eq!(squares.get((4, 3)), SquareKind::Unreachable);
+        assert_eq!(squares.get((-1, 5)), SquareKind::Void);
+
+        // A box can only travel along the top row towards the target
+        let live: Vec<_> = squares.positions(SquareKind::Live).collect();
+        assert_eq!(live, [(1, 4), (1, 5), (1, 6), (1, 7)]);
+        for pos in [(1, 3), (2, 3), (2, 4), (2, 6), (2, 7)] {
+            assert_eq!(squares.get(pos), SquareKind::Dead, "{pos:?}");
+        }
+    }
+}
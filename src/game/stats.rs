@@ -0,0 +1,70 @@
+use std::{fmt, time::Duration};
+
+use crate::game::{GameState, deadlock::DeadlockKind};
+
+/// Move and push counts of a solution, or the best of each over several.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Score {
+    pub moves: usize,
+    pub pushes: usize,
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} moves, {} pushes", self.moves, self.pushes)
+    }
+}
+
+/// How the level being played is going, as shown next to the map.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Stats {
+    /// 1-based position of the level in its collection.
+    pub level: usize,
+    pub name: String,
+    pub moves: usize,
+    pub pushes: usize,
+    pub boxes_on_target: usize,
+    pub boxes: usize,
+    /// Time spent on the level, not counting the time after solving it.
+    pub elapsed: Duration,
+    /// The fewest moves and pushes the level was solved with, if it was.
+    pub best: Option<Score>,
+    /// The shortest time the level was solved in, if it was.
+    pub best_time: Option<Duration>,
+    pub solved: bool,
+    pub deadlock: Option<DeadlockKind>,
+}
+
+/// A level as listed in the level menu.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LevelInfo {
+    /// 1-based position of the level in its collection.
+    pub number: usize,
+    pub name: String,
+    /// Rows and columns of the map.
+    pub size: (i32, i32),
+    pub boxes: usize,
+    /// The fewest moves and pushes the level was solved with, if it was.
+    pub best: Option<Score>,
+    /// The shortest time the level was solved in, if it was.
+    pub best_time: Option<Duration>,
+    /// The starting position, for previews.
+    pub state: GameState,
+}
+
+impl LevelInfo {
+    pub fn is_solved(&self) -> bool {
+        self.best.is_some()
+    }
+}
+
+/// Formats a duration as `m:ss`, or `h:mm:ss` from an hour on.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
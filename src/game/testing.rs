@@ -0,0 +1,17 @@
+//! Levels and helpers shared by the unit tests.
+
+use crate::game::{GameState, level::LevelFormat};
+
+/// The player, a box and a target in a row, solved by pushing right once.
+pub const CORRIDOR: &str = "#####\n#@$.#\n#####";
+
+/// Parses a level in XSB format.
+pub fn xsb(content: &str) -> GameState {
+    GameState::parse_as(content, LevelFormat::Xsb).unwrap()
+}
+
+/// Loads a level from the `levels` directory of the repository.
+pub fn mission(name: &str) -> GameState {
+    let path = format!("{}/levels/{name}", env!("CARGO_MANIFEST_DIR"));
+    GameState::from_file(path).unwrap()
+}
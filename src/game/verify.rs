@@ -0,0 +1,231 @@
+use std::fmt;
+
+use crate::game::{
+    GameState,
+    history::{Move, ParseLurdError, parse_lurd},
+};
+
+/// Why a single move of a solution is not allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IllegalMove {
+    /// The player would leave the map.
+    OutOfBounds,
+    /// The player would walk into a wall.
+    Wall,
+    /// The pushed box would end up in a wall or off the map.
+    BoxIntoWall,
+    /// The pushed box would run into another box.
+    BoxIntoBox,
+    /// The letter is uppercase but there is no box to push.
+    NoBoxToPush,
+    /// The letter is lowercase but the move pushes a box.
+    UnmarkedPush,
+}
+
+impl fmt::Display for IllegalMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IllegalMove::OutOfBounds => write!(f, "the player leaves the map"),
+            IllegalMove::Wall => write!(f, "the player walks into a wall"),
+            IllegalMove::BoxIntoWall => write!(f, "the box is pushed into a wall"),
+            IllegalMove::BoxIntoBox => write!(f, "the box is pushed into another box"),
+            IllegalMove::NoBoxToPush => write!(f, "a push is marked but there is no box"),
+            IllegalMove::UnmarkedPush => write!(f, "a box is pushed by a lowercase move"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    Parse(ParseLurdError),
+    /// Move number `step` (1-based) breaks the rules.
+    Illegal {
+        step: usize,
+        found: char,
+        reason: IllegalMove,
+    },
+    /// Every move is legal but the level is not solved afterwards.
+    NotSolved {
+        moves: usize,
+        pushes: usize,
+    },
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::Parse(err) => write!(f, "{err}"),
+            VerifyError::Illegal {
+                step,
+                found,
+                reason,
+            } => write!(f, "move {step} ({found:?}) is illegal: {reason}"),
+            VerifyError::NotSolved { moves, pushes } => write!(
+                f,
+                "the level is not solved after {moves} moves and {pushes} pushes"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+impl From<ParseLurdError> for VerifyError {
+    fn from(err: ParseLurdError) -> Self {
+        VerifyError::Parse(err)
+    }
+}
+
+/// A solution that was replayed successfully.
+#[derive(Debug, Clone)]
+pub struct Verified {
+    /// The position after the last move.
+    pub state: GameState,
+    pub moves: usize,
+    pub pushes: usize,
+}
+
+/// Replays `lurd` on a copy of `state` and checks that every move is legal
+/// and that the level ends up solved.
+pub fn verify(state: &GameState, lurd: &str) -> Result<Verified, VerifyError> {
+    let moves = parse_lurd(lurd)?;
+    let mut state = state.clone();
+    let mut pushes = 0;
+
+    for (i, &mv) in moves.iter().enumerate() {
+        step(&mut state, mv).map_err(|reason| VerifyError::Illegal {
+            step: i + 1,
+            found: mv.to_char(),
+            reason,
+        })?;
+        if mv.push {
+            pushes += 1;
+        }
+    }
+
+    if !state.is_solved() {
+        return Err(VerifyError::NotSolved {
+            moves: moves.len(),
+            pushes,
+        });
+    }
+
+    Ok(Verified {
+        state,
+        moves: moves.len(),
+        pushes,
+    })
+}
+
+fn step(state: &mut GameState, mv: Move) -> Result<(), IllegalMove> {
+    let (dr, dc) = mv.direction;
+    let (r, c) = state.player_position;
+    let next = (r + dr, c + dc);
+
+    if !state.in_bounds(next) {
+        return Err(IllegalMove::OutOfBounds);
+    }
+    if state.walls.contains(&next) {
+        return Err(IllegalMove::Wall);
+    }
+
+    match state.box_positions.iter().position(|&pos| pos == next) {
+        Some(box_index) => {
+            if !mv.push {
+                return Err(IllegalMove::UnmarkedPush);
+            }
+            let box_next = (next.0 + dr, next.1 + dc);
+            if !state.in_bounds(box_next) || state.walls.contains(&box_next) {
+                return Err(IllegalMove::BoxIntoWall);
+            }
+            if state.box_positions.contains(&box_next) {
+                return Err(IllegalMove::BoxIntoBox);
+            }
+            state.box_positions[box_index] = box_next;
+        }
+        None if mv.push => return Err(IllegalMove::NoBoxToPush),
+        None => {}
+    }
+
+    state.player_position = next;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::testing::xsb;
+
+    /// Two boxes, each one push right of its target.
+    const LEVEL: &str = "######\n#@ $.#\n#  $.#\n######";
+
+    fn illegal(level: &str, lurd: &str) -> Option<(usize, IllegalMove)> {
+        let state = xsb(level);
+        match verify(&state, lurd) {
+            Err(VerifyError::Illegal { step, reason, .. }) => Some((step, reason)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn accepts_a_solution_and_counts_it() {
+        let state = xsb(LEVEL);
+        let verified = verify(&state, "rR ldR").unwrap();
+        assert!(verified.state.is_solved());
+        assert_eq!((verified.moves, verified.pushes), (5, 2));
+    }
+
+    #[test]
+    fn reports_the_first_illegal_move() {
+        assert_eq!(illegal(LEVEL, "u"), Some((1, IllegalMove::Wall)));
+        assert_eq!(illegal(LEVEL, "rr"), Some((2, IllegalMove::UnmarkedPush)));
+        assert_eq!(illegal(LEVEL, "R"), Some((1, IllegalMove::NoBoxToPush)));
+        assert_eq!(illegal(LEVEL, "rRR"), Some((3, IllegalMove::BoxIntoWall)));
+        assert_eq!(
+            illegal("#######\n#@$$..#\n#######", "R"),
+            Some((1, IllegalMove::BoxIntoBox))
+        );
+    }
+
+    #[test]
+    fn reports_the_edge_of_the_map() {
+        // Parsing refuses open maps, so build one by hand
+        let state = GameState::new((0, 0), vec![(0, 1)], vec![(0, 2)], Vec::new(), (1, 3));
+        assert_eq!(
+            verify(&state, "l").err(),
+            Some(VerifyError::Illegal {
+                step: 1,
+                found: 'l',
+                reason: IllegalMove::OutOfBounds
+            })
+        );
+    }
+
+    #[test]
+    fn reports_an_unsolved_level() {
+        let state = xsb(LEVEL);
+        assert_eq!(
+            verify(&state, "rR").err(),
+            Some(VerifyError::NotSolved {
+                moves: 2,
+                pushes: 1
+            })
+        );
+    }
+
+    #[test]
+    fn reports_parse_errors() {
+        let state = xsb(LEVEL);
+        assert!(matches!(
+            verify(&state, "rz"),
+            Err(VerifyError::Parse(ParseLurdError::UnexpectedChar {
+                offset: 1,
+                ..
+            }))
+        ));
+        assert!(matches!(
+            verify(&state, "9999999999r"),
+            Err(VerifyError::Parse(ParseLurdError::TooLong { .. }))
+        ));
+    }
+}
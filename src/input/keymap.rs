@@ -0,0 +1,358 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::{input::InputEvent, rendering::overlay::Overlay};
+
+/// A key together with the modifiers held down with it.
+///
+/// Shift is folded into the character for printable keys, so `shift+z`,
+/// `Z` and a key event for `Z` with the shift modifier are all the same key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Key {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        match code {
+            KeyCode::Char(c) => {
+                let c = if modifiers.contains(KeyModifiers::SHIFT) {
+                    c.to_ascii_uppercase()
+                } else {
+                    c
+                };
+                Key {
+                    code: KeyCode::Char(c),
+                    modifiers: modifiers - KeyModifiers::SHIFT,
+                }
+            }
+            code => Key { code, modifiers },
+        }
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        Key::new(event.code, event.modifiers)
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in MODIFIERS {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{name}+")?;
+            }
+        }
+
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::F(n) => write!(f, "f{n}"),
+            code => match NAMED_KEYS.iter().find(|(_, named)| *named == code) {
+                Some((name, _)) => write!(f, "{name}"),
+                None => write!(f, "{code:?}"),
+            },
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = KeymapError;
+
+    /// Parses keys such as `z`, `ctrl+z`, `shift+up`, `f1` or `space`.
+    /// Modifier and key names are not case sensitive, single characters are.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || KeymapError::InvalidKey(s.to_string());
+
+        let (modifier_names, name) = if s == "+" {
+            ("", "+")
+        } else if let Some(rest) = s.strip_suffix("++") {
+            (rest, "+")
+        } else {
+            s.rsplit_once('+').unwrap_or(("", s))
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier_name in modifier_names.split('+').filter(|part| !part.is_empty()) {
+            let (modifier, _) = MODIFIERS
+                .iter()
+                .find(|(_, name)| name.eq_ignore_ascii_case(modifier_name.trim()))
+                .ok_or_else(invalid)?;
+            modifiers |= *modifier;
+        }
+
+        let mut chars = name.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => {
+                let lower = name.trim().to_ascii_lowercase();
+                if lower == "space" {
+                    KeyCode::Char(' ')
+                } else if let Some(n) = lower.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    KeyCode::F(n)
+                } else {
+                    NAMED_KEYS
+                        .iter()
+                        .find(|(named, _)| *named == lower)
+                        .map(|(_, code)| *code)
+                        .ok_or_else(invalid)?
+                }
+            }
+        };
+
+        Ok(Key::new(code, modifiers))
+    }
+}
+
+const MODIFIERS: [(KeyModifiers, &str); 3] = [
+    (KeyModifiers::CONTROL, "ctrl"),
+    (KeyModifiers::ALT, "alt"),
+    (KeyModifiers::SHIFT, "shift"),
+];
+
+const NAMED_KEYS: [(&str, KeyCode); 14] = [
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("enter", KeyCode::Enter),
+    ("esc", KeyCode::Esc),
+    ("backspace", KeyCode::Backspace),
+    ("tab", KeyCode::Tab),
+    ("delete", KeyCode::Delete),
+    ("insert", KeyCode::Insert),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeymapError {
+    InvalidKey(String),
+    UnknownAction(String),
+    /// The same key was bound to two actions.
+    Conflict {
+        key: Key,
+        first: InputEvent,
+        second: InputEvent,
+    },
+}
+
+impl fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeymapError::InvalidKey(key) => write!(f, "invalid key {key:?}"),
+            KeymapError::UnknownAction(action) => write!(f, "unknown action {action:?}"),
+            KeymapError::Conflict { key, first, second } => {
+                write!(f, "key {key} is bound to both {first} and {second}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for KeymapError {}
+
+/// Which keys trigger which `InputEvent`.
+///
+/// The default bindings cover the arrow keys, WASD, vim's `hjkl` and the
+/// number pad (`8`, `4`, `2`, `6` with num lock on, arrows with it off).
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    /// Keys per action, in the order of `InputEvent::ALL`.
+    keys: Vec<(InputEvent, Vec<Key>)>,
+    lookup: HashMap<Key, InputEvent>,
+}
+
+impl Keymap {
+    /// Builds a keymap, failing if a key is bound to more than one action.
+    pub fn new(
+        bindings: impl IntoIterator<Item = (InputEvent, Vec<Key>)>,
+    ) -> Result<Self, KeymapError> {
+        let mut by_action: HashMap<InputEvent, Vec<Key>> = bindings.into_iter().collect();
+        let keys: Vec<(InputEvent, Vec<Key>)> = InputEvent::ALL
+            .into_iter()
+            .map(|action| (action, by_action.remove(&action).unwrap_or_default()))
+            .collect();
+
+        let mut lookup = HashMap::new();
+        for (action, action_keys) in &keys {
+            for &key in action_keys {
+                if let Some(first) = lookup.insert(key, *action)
+                    && first != *action
+                {
+                    return Err(KeymapError::Conflict {
+                        key,
+                        first,
+                        second: *action,
+                    });
+                }
+            }
+        }
+
+        Ok(Keymap { keys, lookup })
+    }
+
+    /// Replaces the keys of every action named in `overrides` and keeps the
+    /// rest. Keys are written as in `Key::from_str`.
+    pub fn with_overrides(
+        &self,
+        overrides: &HashMap<String, Vec<String>>,
+    ) -> Result<Self, KeymapError> {
+        let mut bindings = self.keys.clone();
+
+        for (name, keys) in overrides {
+            let action: InputEvent = name
+                .parse()
+                .map_err(|_| KeymapError::UnknownAction(name.clone()))?;
+            let keys = keys
+                .iter()
+                .map(|key| key.parse())
+                .collect::<Result<Vec<Key>, _>>()?;
+            if let Some((_, action_keys)) = bindings.iter_mut().find(|(a, _)| *a == action) {
+                *action_keys = keys;
+            }
+        }
+
+        Keymap::new(bindings)
+    }
+
+    pub fn get(&self, key: Key) -> Option<InputEvent> {
+        self.lookup.get(&key).copied()
+    }
+
+    /// The action bound to a key event, if any.
+    pub fn lookup(&self, event: KeyEvent) -> Option<InputEvent> {
+        self.get(Key::from(event))
+    }
+
+    /// The keys bound to `action`, the preferred one first.
+    pub fn keys(&self, action: InputEvent) -> &[Key] {
+        self.keys
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map_or(&[], |(_, keys)| keys.as_slice())
+    }
+
+    /// Every action with its keys, in the order of `InputEvent::ALL`.
+    pub fn bindings(&self) -> impl Iterator<Item = (InputEvent, &[Key])> {
+        self.keys
+            .iter()
+            .map(|(action, keys)| (*action, keys.as_slice()))
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let defaults: [(InputEvent, &[&str]); 20] = [
+            (InputEvent::MoveUp, &["up", "w", "k", "8"]),
+            (InputEvent::MoveDown, &["down", "s", "j", "2"]),
+            (InputEvent::MoveLeft, &["left", "a", "h", "4"]),
+            (InputEvent::MoveRight, &["right", "d", "l", "6"]),
+            (InputEvent::Undo, &["z", "u", "ctrl+z", "backspace"]),
+            (InputEvent::Redo, &["x", "ctrl+y", "ctrl+r"]),
+            (InputEvent::Restart, &["r"]),
+            (InputEvent::NextLevel, &["n", "enter"]),
+            (InputEvent::Menu, &["g", "f10"]),
+            (InputEvent::Hint, &["i", "f4"]),
+            (InputEvent::Help, &["?", "f1"]),
+            (InputEvent::Zoom, &["v", "f2"]),
+            (InputEvent::Theme, &["t", "f3"]),
+            (InputEvent::Overlay(Overlay::DeadSquares), &["e", "f5"]),
+            (InputEvent::Overlay(Overlay::Reachable), &["m", "f6"]),
+            (InputEvent::Overlay(Overlay::PushReachable), &["p", "f7"]),
+            (InputEvent::Overlay(Overlay::HintPath), &["o", "f8"]),
+            (InputEvent::Overlay(Overlay::Frozen), &["f", "f9"]),
+            (InputEvent::SelectBox, &["b", "tab"]),
+            (InputEvent::Quit, &["q", "esc", "ctrl+c"]),
+        ];
+
+        let bindings = defaults.into_iter().map(|(action, keys)| {
+            let keys = keys
+                .iter()
+                .map(|key| key.parse().expect("default keys are valid"))
+                .collect();
+            (action, keys)
+        });
+        Keymap::new(bindings).expect("default keys do not conflict")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(s: &str) -> Key {
+        s.parse().unwrap()
+    }
+
+    fn overrides(bindings: &[(&str, &[&str])]) -> HashMap<String, Vec<String>> {
+        bindings
+            .iter()
+            .map(|(action, keys)| {
+                let keys = keys.iter().map(|key| key.to_string()).collect();
+                (action.to_string(), keys)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn keys_parse_with_modifiers_and_names() {
+        assert_eq!(key("z"), Key::new(KeyCode::Char('z'), KeyModifiers::NONE));
+        assert_eq!(
+            key("Ctrl+Z"),
+            Key::new(KeyCode::Char('Z'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(key("shift+z"), key("Z"));
+        assert_eq!(key("shift+UP"), Key::new(KeyCode::Up, KeyModifiers::SHIFT));
+        assert_eq!(key("f12"), Key::new(KeyCode::F(12), KeyModifiers::NONE));
+        assert_eq!(key("space"), key(" "));
+        assert_eq!(key("+"), Key::new(KeyCode::Char('+'), KeyModifiers::NONE));
+        assert_eq!(
+            key("ctrl++"),
+            Key::new(KeyCode::Char('+'), KeyModifiers::CONTROL)
+        );
+
+        for invalid in ["hyper+z", "upp", "ctrl+", ""] {
+            assert_eq!(
+                invalid.parse::<Key>(),
+                Err(KeymapError::InvalidKey(invalid.to_string())),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn keys_display_as_they_are_parsed() {
+        for name in ["z", "ctrl+z", "alt+shift+left", "f4", "space", "pagedown"] {
+            assert_eq!(key(name).to_string(), name);
+        }
+    }
+
+    #[test]
+    fn overrides_replace_the_keys_of_an_action() {
+        let keymap = Keymap::default()
+            .with_overrides(&overrides(&[("undo", &["y"])]))
+            .unwrap();
+
+        assert_eq!(keymap.keys(InputEvent::Undo), [key("y")]);
+        assert_eq!(keymap.get(key("y")), Some(InputEvent::Undo));
+        assert_eq!(keymap.get(key("z")), None);
+        assert_eq!(keymap.get(key("x")), Some(InputEvent::Redo));
+    }
+
+    #[test]
+    fn a_key_bound_twice_is_a_conflict() {
+        let result = Keymap::default().with_overrides(&overrides(&[("undo", &["r"])]));
+        assert!(matches!(
+            result,
+            Err(KeymapError::Conflict { key: conflict, .. }) if conflict == key("r")
+        ));
+
+        let unknown = Keymap::default().with_overrides(&overrides(&[("fly", &["y"])]));
+        assert!(matches!(unknown, Err(KeymapError::UnknownAction(name)) if name == "fly"));
+    }
+}
@@ -1,4 +1,10 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use std::{fmt, str::FromStr};
+
+use crate::rendering::overlay::Overlay;
+
+pub mod keymap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum InputEvent {
     MoveUp,
     MoveDown,
@@ -7,5 +13,114 @@ pub enum InputEvent {
     Undo,
     Redo,
     Restart,
+    NextLevel,
+    /// Opens the level menu.
+    Menu,
+    /// Starts the level with the given 1-based number, e.g. after picking
+    /// it from the level menu.
+    GoToLevel(usize),
+    /// Asks the solver for the next push.
+    Hint,
+    /// Shows or hides the list of key bindings. Handled by the front end.
+    Help,
+    /// Cycles through the ways of drawing the map. Handled by the front end.
+    Zoom,
+    /// Switches to the next built-in theme. Handled by the front end.
+    Theme,
+    /// Shows or hides one of the analysis overlays. Handled by the front end.
+    Overlay(Overlay),
+    /// Selects the next box for the push-reachability overlay. Handled by
+    /// the front end.
+    SelectBox,
     Quit,
+    /// Walk to the given cell along a shortest path, e.g. after a click.
+    WalkTo((i32, i32)),
+    /// Push the box at `from` to `to`, e.g. after dragging it with the mouse.
+    PushBox {
+        from: (i32, i32),
+        to: (i32, i32),
+    },
+}
+
+impl InputEvent {
+    /// Every event that can be bound to a key.
+    pub const ALL: [InputEvent; 20] = [
+        InputEvent::MoveUp,
+        InputEvent::MoveDown,
+        InputEvent::MoveLeft,
+        InputEvent::MoveRight,
+        InputEvent::Undo,
+        InputEvent::Redo,
+        InputEvent::Restart,
+        InputEvent::NextLevel,
+        InputEvent::Menu,
+        InputEvent::Hint,
+        InputEvent::Help,
+        InputEvent::Zoom,
+        InputEvent::Theme,
+        InputEvent::Overlay(Overlay::DeadSquares),
+        InputEvent::Overlay(Overlay::Reachable),
+        InputEvent::Overlay(Overlay::PushReachable),
+        InputEvent::Overlay(Overlay::HintPath),
+        InputEvent::Overlay(Overlay::Frozen),
+        InputEvent::SelectBox,
+        InputEvent::Quit,
+    ];
+
+    /// The `(row, col)` offset of a move event.
+    pub fn direction(&self) -> Option<(i32, i32)> {
+        match self {
+            InputEvent::MoveUp => Some((-1, 0)),
+            InputEvent::MoveDown => Some((1, 0)),
+            InputEvent::MoveLeft => Some((0, -1)),
+            InputEvent::MoveRight => Some((0, 1)),
+            _ => None,
+        }
+    }
+
+    /// The name used for the event in config files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            InputEvent::MoveUp => "move_up",
+            InputEvent::MoveDown => "move_down",
+            InputEvent::MoveLeft => "move_left",
+            InputEvent::MoveRight => "move_right",
+            InputEvent::Undo => "undo",
+            InputEvent::Redo => "redo",
+            InputEvent::Restart => "restart",
+            InputEvent::NextLevel => "next_level",
+            InputEvent::Menu => "menu",
+            InputEvent::GoToLevel(_) => "go_to_level",
+            InputEvent::Hint => "hint",
+            InputEvent::Help => "help",
+            InputEvent::Zoom => "zoom",
+            InputEvent::Theme => "theme",
+            InputEvent::Overlay(Overlay::DeadSquares) => "show_dead_squares",
+            InputEvent::Overlay(Overlay::Reachable) => "show_reachable",
+            InputEvent::Overlay(Overlay::PushReachable) => "show_push_reachable",
+            InputEvent::Overlay(Overlay::HintPath) => "show_hint_path",
+            InputEvent::Overlay(Overlay::Frozen) => "show_frozen",
+            InputEvent::SelectBox => "select_box",
+            InputEvent::Quit => "quit",
+            InputEvent::WalkTo(_) => "walk_to",
+            InputEvent::PushBox { .. } => "push_box",
+        }
+    }
+}
+
+impl fmt::Display for InputEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for InputEvent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        InputEvent::ALL
+            .into_iter()
+            .find(|event| event.name() == s)
+            .ok_or_else(|| format!("unknown action {s:?}"))
+    }
 }
@@ -1,3 +1,6 @@
+pub mod config;
 pub mod game;
 pub mod input;
+pub mod progress;
 pub mod rendering;
+pub mod session;
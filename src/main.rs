@@ -1,7 +1,16 @@
-use sokoban_rs::{game, rendering::cli::CliFrontEnd};
+use std::process::ExitCode;
 
-fn main() {
-    let mut game =
-        game::Game::<CliFrontEnd>::new(game::GameState::from_file("levels/mission3.txt".into()));
-    game.run();
+use clap::Parser;
+
+mod cli;
+
+fn main() -> ExitCode {
+    match cli::run(cli::Cli::parse()) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -0,0 +1,252 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{GameState, stats::Score};
+
+/// What the player achieved on one level, over all sessions.
+///
+/// The best move count, push count and time are kept separately, so they
+/// may come from different solutions. `solution` is the one with the fewest
+/// moves.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    /// The level's name when it was last solved, to make the file readable.
+    pub name: String,
+    pub solved: bool,
+    pub best_moves: Option<usize>,
+    pub best_pushes: Option<usize>,
+    /// The shortest time the level was solved in, in whole seconds.
+    pub best_time: Option<u64>,
+    /// The solution with the fewest moves, in LURD notation.
+    pub solution: Option<String>,
+}
+
+impl Record {
+    pub fn best(&self) -> Option<Score> {
+        Some(Score {
+            moves: self.best_moves?,
+            pushes: self.best_pushes?,
+        })
+    }
+
+    pub fn best_time(&self) -> Option<Duration> {
+        self.best_time.map(Duration::from_secs)
+    }
+}
+
+/// A finished level, as reported to `Progress::record`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub name: String,
+    pub moves: usize,
+    pub pushes: usize,
+    pub time: Duration,
+    pub lurd: String,
+}
+
+/// The player's records for every level they solved, saved as JSON in the
+/// user data directory.
+///
+/// Levels are looked up by a hash of their content, so records survive
+/// renaming or moving level files, and the same level in two collections
+/// shares one record.
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    /// Where the records are saved, or `None` to keep them in memory.
+    path: Option<PathBuf>,
+    levels: BTreeMap<String, Record>,
+}
+
+/// The file as stored on disk.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ProgressFile {
+    #[serde(default)]
+    levels: BTreeMap<String, Record>,
+}
+
+#[derive(Debug)]
+pub enum ProgressError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+}
+
+impl fmt::Display for ProgressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProgressError::Io(err) => write!(f, "failed to access progress: {err}"),
+            ProgressError::Parse(err) => write!(f, "invalid progress file: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ProgressError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ProgressError::Io(err) => Some(err),
+            ProgressError::Parse(err) => Some(err),
+        }
+    }
+}
+
+impl From<std::io::Error> for ProgressError {
+    fn from(err: std::io::Error) -> Self {
+        ProgressError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ProgressError {
+    fn from(err: serde_json::Error) -> Self {
+        ProgressError::Parse(err)
+    }
+}
+
+impl Progress {
+    /// Where progress is kept, e.g. `~/.local/share/sokoban-rs/progress.json`
+    /// on Linux.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("sokoban-rs").join("progress.json"))
+    }
+
+    /// Reads the progress from the default path. Without a data directory
+    /// the records are only kept in memory.
+    pub fn load() -> Result<Self, ProgressError> {
+        match Self::default_path() {
+            Some(path) => Self::from_file(path),
+            None => Ok(Progress::default()),
+        }
+    }
+
+    /// Reads the progress saved at `file_path` and saves back to it. A
+    /// missing file is not an error and gives no records.
+    pub fn from_file(file_path: impl Into<PathBuf>) -> Result<Self, ProgressError> {
+        let path = file_path.into();
+        let file: ProgressFile = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => ProgressFile::default(),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Progress {
+            path: Some(path),
+            levels: file.levels,
+        })
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Writes the records back to the file they were read from, creating
+    /// its directory if needed.
+    pub fn save(&self) -> Result<(), ProgressError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let file = ProgressFile {
+            levels: self.levels.clone(),
+        };
+        std::fs::write(path, serde_json::to_string_pretty(&file)?)?;
+        Ok(())
+    }
+
+    /// The records of the level that starts in `state`.
+    pub fn get(&self, state: &GameState) -> Option<&Record> {
+        self.levels.get(&level_key(state))
+    }
+
+    /// Adds a solution of the level that starts in `state`, keeping each
+    /// best value. Returns whether it set a new record.
+    pub fn record(&mut self, state: &GameState, completion: Completion) -> bool {
+        let record = self.levels.entry(level_key(state)).or_default();
+        let better_moves = improves(record.best_moves, completion.moves);
+        let better_pushes = improves(record.best_pushes, completion.pushes);
+        let better_time = improves(record.best_time, completion.time.as_secs());
+
+        record.name = completion.name;
+        record.solved = true;
+        if better_moves {
+            record.best_moves = Some(completion.moves);
+            record.solution = Some(completion.lurd);
+        }
+        if better_pushes {
+            record.best_pushes = Some(completion.pushes);
+        }
+        if better_time {
+            record.best_time = Some(completion.time.as_secs());
+        }
+
+        better_moves || better_pushes || better_time
+    }
+}
+
+/// Whether `value` beats the record `best`, lower being better.
+fn improves<T: PartialOrd>(best: Option<T>, value: T) -> bool {
+    best.is_none_or(|best| value < best)
+}
+
+/// Identifies a level by its content: a 64-bit FNV-1a hash of its XSB
+/// rows without trailing spaces, as 16 hex digits. FNV is used rather than
+/// the standard hasher because its output never changes between releases.
+pub fn level_key(state: &GameState) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for line in state.to_xsb().lines() {
+        for byte in line.trim_end().bytes().chain([b'\n']) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+    format!("{hash:016x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::testing::{CORRIDOR, xsb};
+
+    fn completion(moves: usize, pushes: usize, secs: u64, lurd: &str) -> Completion {
+        Completion {
+            name: String::from("Corridor"),
+            moves,
+            pushes,
+            time: Duration::from_secs(secs),
+            lurd: lurd.to_string(),
+        }
+    }
+
+    #[test]
+    fn record_keeps_the_best_of_each_value() {
+        let state = xsb(CORRIDOR);
+        let mut progress = Progress::default();
+
+        assert!(progress.record(&state, completion(5, 3, 40, "first")));
+        assert!(progress.record(&state, completion(7, 2, 50, "second")));
+        assert!(!progress.record(&state, completion(9, 4, 60, "third")));
+
+        let record = progress.get(&state).unwrap();
+        assert_eq!(
+            record.best(),
+            Some(Score {
+                moves: 5,
+                pushes: 2
+            })
+        );
+        assert_eq!(record.best_time(), Some(Duration::from_secs(40)));
+        assert_eq!(record.solution.as_deref(), Some("first"));
+    }
+
+    #[test]
+    fn level_key_ignores_trailing_spaces() {
+        let padded = xsb("#####  \n#@$.#\n#####  ");
+        assert_eq!(level_key(&padded), level_key(&xsb(CORRIDOR)));
+    }
+}
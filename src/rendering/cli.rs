@@ -1,30 +1,122 @@
+use std::time::Duration;
+
 use ratatui::{
-    crossterm::event::{self, Event, KeyCode},
-    layout::Rect,
+    crossterm::{
+        event::{
+            self, DisableMouseCapture, EnableMouseCapture, Event, KeyEventKind, MouseButton,
+            MouseEvent, MouseEventKind,
+        },
+        execute,
+    },
+    layout::{Constraint, Layout, Rect},
     style::Stylize,
-    symbols::{block, border},
-    text::{Line, Span, ToSpan},
-    widgets::{Block, Paragraph, Table},
+    symbols::border,
+    text::Line,
+    widgets::{Block, Clear, Paragraph, Wrap},
+};
+
+use crate::{
+    game::{
+        GameState,
+        hint::Hint,
+        solver::SolveError,
+        stats::{Stats, format_duration},
+    },
+    input::{InputEvent, keymap::Keymap},
+    rendering::{
+        FrontEnd, FrontEndEvent, GameEvent,
+        map::{Viewport, Zoom},
+        menu::{LevelMenu, MenuAction},
+        overlay::{Layers, Overlay, Overlays},
+        theme::Theme,
+    },
 };
 
-use crate::{input::InputEvent, rendering::FrontEnd};
+/// How wide the status panel next to the map is, borders included.
+const SIDEBAR_WIDTH: u16 = 26;
+
+/// How wide the map area has to be to make room for the status panel.
+const SIDEBAR_MIN_AREA: u16 = SIDEBAR_WIDTH + 20;
 
 pub struct CliFrontEnd {
     terminal: ratatui::DefaultTerminal,
+    title: String,
+    pub keymap: Keymap,
+    show_help: bool,
+    /// Set when the screen changed without the game state changing.
+    redraw: bool,
+    zoom: Zoom,
+    theme: Theme,
+    overlays: Overlays,
+    /// The index of the box whose push-reachable cells are shown.
+    selected_box: Option<usize>,
+    /// The level menu, while it is open.
+    menu: Option<LevelMenu>,
+    /// The answer to the last request for a hint, until the next move.
+    hint: Option<Result<Hint, SolveError>>,
+    /// Where the boxes were when the map was last drawn, for clicks.
+    boxes: Vec<(i32, i32)>,
+    /// The map cell in the top left corner when the map does not fit.
+    scroll: (i32, i32),
+    /// Where the map was last drawn, for turning mouse positions into cells.
+    viewport: Option<Viewport>,
+    /// The cell the left mouse button went down on.
+    drag_start: Option<(i32, i32)>,
 }
 
 impl FrontEnd for CliFrontEnd {
-    fn render(&mut self, state: &crate::game::GameState) {
+    fn render(&mut self, state: &GameState, stats: &Stats) {
+        self.redraw = false;
+        if let Some(menu) = &self.menu {
+            let theme = &self.theme;
+            self.terminal
+                .draw(|frame| menu.render(frame, theme))
+                .unwrap();
+            return;
+        }
+
+        let key = |action| {
+            self.keymap
+                .keys(action)
+                .first()
+                .map_or_else(|| String::from("?"), ToString::to_string)
+        };
+        let help_key = key(InputEvent::Help);
+        let quit_key = key(InputEvent::Quit);
+        let restart_key = key(InputEvent::Restart);
+        let next_key = key(InputEvent::NextLevel);
+        let menu_key = key(InputEvent::Menu);
+        let help = self.show_help.then(|| help_lines(&self.keymap));
+        let hint = self.hint.as_ref().and_then(|hint| hint.as_ref().ok());
+        let layers = if self.overlays.any() {
+            Layers::new(state, self.overlays, self.selected_box, hint)
+        } else {
+            Layers::default()
+        };
+        let mut sidebar = stats_lines(stats);
+        if let Some(hint) = &self.hint {
+            sidebar.extend(hint_lines(hint));
+        }
+        sidebar.extend(self.legend_lines());
+        self.boxes.clone_from(&state.box_positions);
+
         self.terminal
             .draw(|frame| {
-                let title = Line::raw("Sokoban");
+                let mut title = self.title.clone();
+                if self.zoom != Zoom::Auto {
+                    title.push_str(&format!(" [{}]", self.zoom.name()));
+                }
+                if self.theme.name != "classic" {
+                    title.push_str(&format!(" [{}]", self.theme.name));
+                }
+                let title = Line::raw(title);
                 let instructions = {
-                    if state.is_solved() {
-                        Line::raw("You solved the puzzle! Press 'r' to restart or 'q' to quit.")
-                    } else if state.is_deadlock() {
-                        Line::raw("Deadlock detected! Press 'r' to restart or 'q' to quit.")
+                    if stats.solved {
+                        Line::raw(format!("You solved the puzzle! Press '{next_key}' for the next level, '{restart_key}' to restart or '{quit_key}' to quit."))
+                    } else if let Some(reason) = &stats.deadlock {
+                        Line::raw(format!("Deadlock detected: {reason}! Press '{restart_key}' to restart or '{quit_key}' to quit."))
                     } else {
-                        Line::raw("Use arrow keys or WASD to move, 'z' to undo, 'x' to redo, 'r' to restart, 'q' to quit.")
+                        Line::raw(format!("Press '{help_key}' to see the keys, '{menu_key}' for the levels, '{quit_key}' to quit."))
                     }
                 };
                 let block = Block::bordered()
@@ -32,94 +124,324 @@ impl FrontEnd for CliFrontEnd {
                     .title_bottom(instructions.centered())
                     .border_set(border::THICK);
 
-                let game_area = block.inner(frame.area());
+                let mut game_area = block.inner(frame.area());
                 frame.render_widget(block, frame.area());
 
-                let (map_rows, map_cols) = state.map_size;
+                if game_area.width >= SIDEBAR_MIN_AREA {
+                    let [map_area, sidebar_area] =
+                        Layout::horizontal([Constraint::Min(0), Constraint::Length(SIDEBAR_WIDTH)])
+                            .areas(game_area);
+                    game_area = map_area;
+                    let sidebar = Paragraph::new(sidebar)
+                        .wrap(Wrap { trim: true })
+                        .block(Block::bordered().title(Line::raw("Status").centered()));
+                    frame.render_widget(sidebar, sidebar_area);
+                }
+
+                self.viewport = Viewport::new(state, &self.theme, game_area, self.zoom, self.scroll);
+                if let Some(viewport) = self.viewport {
+                    self.scroll = viewport.scroll;
+                    frame.render_widget(Paragraph::new(viewport.lines(state, &self.theme, &layers)), viewport.area);
+                }
 
-                if game_area.width < map_cols as u16 || game_area.height < map_rows as u16 {
-                    return; // Not enough space to render the game area
+                if let Some(help) = help {
+                    let width = help.iter().map(Line::width).max().unwrap_or(0) as u16 + 2;
+                    let height = help.len() as u16 + 2;
+                    let area = frame.area();
+                    let popup = Rect {
+                        x: area.x + area.width.saturating_sub(width) / 2,
+                        y: area.y + area.height.saturating_sub(height) / 2,
+                        width: width.min(area.width),
+                        height: height.min(area.height),
+                    };
+                    let block = Block::bordered()
+                        .title(Line::raw("Keys").centered())
+                        .border_set(border::THICK);
+                    frame.render_widget(Clear, popup);
+                    frame.render_widget(Paragraph::new(help).block(block), popup);
                 }
+            })
+            .unwrap();
+    }
+    fn poll_event(&mut self, timeout: Duration) -> Option<FrontEndEvent> {
+        if !event::poll(timeout).ok()? {
+            return None;
+        }
 
-                let mut game_table: Vec<Line> = Vec::with_capacity(map_rows as usize);
-
-
-                for r in 0..map_rows {
-                    let mut row_span = Vec::with_capacity(map_cols as usize);
-                    for c in 0..map_cols {
-                        let pos = (r, c);
-                        let symbol = if pos == state.player_position {
-                            "P".blue().bold()
-                        } else if state.box_positions.contains(&pos) {
-                            if state.target_positions.contains(&pos) {
-                                "*".on_red()
-                            } else {
-                                "$".yellow()
-                            }
-                        } else if state.target_positions.contains(&pos) {
-                            ".".red()
-                        } else if state.walls.contains(&pos) {
-                            "#".white()
-
-                        } else if state.box_route.contains(&pos) {
-                            "O".green()
-                        } else if state.dead_pos.contains(&pos) {
-                            "X".red()
-                        } else {
-                            " ".white()
-                        };
-
-                        row_span.push(symbol);
+        match event::read().ok()? {
+            Event::Key(key_event)
+                if key_event.kind == KeyEventKind::Press && self.menu.is_some() =>
+            {
+                let menu = self.menu.as_mut()?;
+                self.redraw = true;
+                match menu.handle_key(key_event) {
+                    MenuAction::Redraw => None,
+                    MenuAction::Close => {
+                        self.menu = None;
+                        None
                     }
-                    game_table.push(Line::from(row_span));
+                    MenuAction::Play(number) => {
+                        self.menu = None;
+                        Some(FrontEndEvent::Input(InputEvent::GoToLevel(number)))
+                    }
+                    MenuAction::Quit => Some(FrontEndEvent::Input(InputEvent::Quit)),
+                }
+            }
+            Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
+                match self.keymap.lookup(key_event)? {
+                    InputEvent::Help => {
+                        self.show_help = !self.show_help;
+                        self.redraw = true;
+                        None
+                    }
+                    InputEvent::Zoom => {
+                        self.zoom = self.zoom.next();
+                        self.redraw = true;
+                        None
+                    }
+                    InputEvent::Theme => {
+                        self.next_theme();
+                        self.redraw = true;
+                        None
+                    }
+                    InputEvent::Overlay(overlay) => {
+                        self.overlays.toggle(overlay);
+                        self.redraw = true;
+                        None
+                    }
+                    InputEvent::SelectBox => {
+                        let next = self.selected_box.map_or(0, |index| index + 1);
+                        self.select_box((next < self.boxes.len()).then_some(next));
+                        None
+                    }
+                    input => Some(FrontEndEvent::Input(input)),
                 }
+            }
+            Event::Mouse(_) if self.menu.is_some() => None,
+            Event::Mouse(mouse_event) => self.handle_mouse(mouse_event),
+            Event::Resize(width, height) => Some(FrontEndEvent::Resize { width, height }),
+            _ => None,
+        }
+    }
 
-                let game_widget = Paragraph::new(game_table);
+    fn needs_redraw(&self) -> bool {
+        self.redraw
+    }
 
-                let centered_area = Rect {
-                    x: game_area.x + (game_area.width - map_cols as u16) / 2,
-                    y: game_area.y + (game_area.height - map_rows as u16) / 2,
-                    width: map_cols as u16,
-                    height: map_rows as u16,
+    fn game_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::LevelLoaded { number, name } => {
+                self.title = if *name == format!("Level {number}") {
+                    format!("Sokoban - Level {number}")
+                } else {
+                    format!("Sokoban - Level {number}: {name}")
                 };
+                self.hint = None;
+            }
+            GameEvent::Moved(_) | GameEvent::Undone(_) | GameEvent::Restarted => {
+                self.hint = None;
+            }
+            GameEvent::Hint(hint) => {
+                self.hint = Some(hint.clone());
+                self.redraw = true;
+            }
+            GameEvent::ShowLevels { levels, current } => {
+                self.menu = Some(LevelMenu::new(levels.clone(), *current));
+                self.redraw = true;
+            }
+            _ => {}
+        }
+    }
+}
 
-                frame.render_widget(game_widget, centered_area);
-            })
-            .unwrap();
+impl CliFrontEnd {
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.redraw = true;
     }
-    fn get_input(&self) -> Option<InputEvent> {
-        // first check if there is an event available
-        // so we write Ok to the event::poll function
-        if let Ok(true) = event::poll(std::time::Duration::from_millis(100)) {
-            // if there is an event, read it
-            if let Ok(Event::Key(key_event)) = event::read() {
-                match key_event.code {
-                    KeyCode::Up | KeyCode::Char('w') => return Some(InputEvent::MoveUp),
-                    KeyCode::Down | KeyCode::Char('s') => return Some(InputEvent::MoveDown),
-                    KeyCode::Left | KeyCode::Char('a') => return Some(InputEvent::MoveLeft),
-                    KeyCode::Right | KeyCode::Char('d') => return Some(InputEvent::MoveRight),
-                    KeyCode::Esc | KeyCode::Char('q') => return Some(InputEvent::Quit),
-                    KeyCode::Char('z') => return Some(InputEvent::Undo),
-                    KeyCode::Char('x') => return Some(InputEvent::Redo),
-                    KeyCode::Char('r') => return Some(InputEvent::Restart),
-                    _ => return None,
-                }
+
+    /// Selects a box and shows where it can be pushed, or clears the
+    /// selection.
+    fn select_box(&mut self, index: Option<usize>) {
+        self.selected_box = index;
+        self.overlays.set(Overlay::PushReachable, index.is_some());
+        self.redraw = true;
+    }
+
+    /// The overlay legend for the status panel: each overlay's symbol, name
+    /// and key, with the ones that are off dimmed.
+    fn legend_lines(&self) -> Vec<Line<'static>> {
+        let mut lines = vec![Line::raw(""), Line::from(" Overlays".bold())];
+        for overlay in Overlay::ALL {
+            let key = self
+                .keymap
+                .keys(InputEvent::Overlay(overlay))
+                .first()
+                .map_or_else(String::new, |key| format!(" [{key}]"));
+            let mut line = Line::from(vec![
+                " ".into(),
+                self.theme.span(overlay.tile()),
+                format!(" {}{key}", overlay.name()).into(),
+            ]);
+            if !self.overlays.is_on(overlay) {
+                line = line.dim();
             }
+            lines.push(line);
         }
-        None
+        lines
     }
+
+    /// Moves on to the built-in theme after the current one. A theme loaded
+    /// from a file is left for the first built-in one.
+    fn next_theme(&mut self) {
+        let names = Theme::NAMES;
+        let next = names
+            .iter()
+            .position(|name| *name == self.theme.name)
+            .map_or(0, |index| (index + 1) % names.len());
+        if let Some(theme) = Theme::named(names[next]) {
+            self.theme = theme;
+        }
+    }
+
+    /// A click walks to the clicked cell or selects the clicked box,
+    /// dragging from one cell to another pushes the box on the first cell
+    /// there.
+    fn handle_mouse(&mut self, mouse_event: MouseEvent) -> Option<FrontEndEvent> {
+        let cell = self
+            .viewport
+            .and_then(|viewport| viewport.cell_at(mouse_event.column, mouse_event.row));
+
+        match mouse_event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.drag_start = cell;
+                None
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                let from = self.drag_start.take()?;
+                let to = cell?;
+                let input = if from == to {
+                    if let Some(index) = self.boxes.iter().position(|&pos| pos == to) {
+                        // Clicking a box again clears the selection
+                        let selected = self.selected_box != Some(index);
+                        self.select_box(selected.then_some(index));
+                        return None;
+                    }
+                    InputEvent::WalkTo(to)
+                } else {
+                    InputEvent::PushBox { from, to }
+                };
+                Some(FrontEndEvent::Input(input))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The contents of the status panel.
+fn stats_lines(stats: &Stats) -> Vec<Line<'static>> {
+    let row =
+        |label: &str, value: String| Line::from(vec![format!(" {label:<8}").bold(), value.into()]);
+
+    let mut lines = vec![Line::from(format!(" Level {}", stats.level).bold())];
+    if stats.name != format!("Level {}", stats.level) {
+        lines.push(Line::raw(format!(" {}", stats.name)));
+    }
+    lines.push(Line::raw(""));
+    lines.push(row("Moves", stats.moves.to_string()));
+    lines.push(row("Pushes", stats.pushes.to_string()));
+    lines.push(row(
+        "Boxes",
+        format!("{}/{}", stats.boxes_on_target, stats.boxes),
+    ));
+    lines.push(row("Time", format_duration(stats.elapsed)));
+    lines.push(row(
+        "Best",
+        stats.best.map_or_else(
+            || String::from("-"),
+            |best| format!("{}/{}", best.moves, best.pushes),
+        ),
+    ));
+    lines.push(row(
+        "Fastest",
+        stats
+            .best_time
+            .map_or_else(|| String::from("-"), format_duration),
+    ));
+    lines.push(Line::raw(""));
+
+    if stats.solved {
+        lines.push(Line::from(" Solved!".green().bold()));
+    } else if let Some(deadlock) = &stats.deadlock {
+        lines.push(Line::from(" Deadlocked".red().bold()));
+        lines.push(Line::raw(format!(" {deadlock}")));
+    } else {
+        lines.push(Line::raw(" No deadlock"));
+    }
+    lines
+}
+
+/// The answer to a request for a hint, for the status panel.
+fn hint_lines(hint: &Result<Hint, SolveError>) -> Vec<Line<'static>> {
+    let text = match hint {
+        Ok(hint) => format!(" {hint}"),
+        Err(SolveError::Unsolvable) => String::from(" This position cannot be solved"),
+        Err(SolveError::NodeLimit(_) | SolveError::Cancelled) => {
+            String::from(" The solver found no hint in time")
+        }
+    };
+    vec![Line::raw(""), Line::from(" Hint".bold()), Line::raw(text)]
+}
+
+/// One line per action listing every key bound to it.
+fn help_lines(keymap: &Keymap) -> Vec<Line<'static>> {
+    let width = InputEvent::ALL
+        .iter()
+        .map(|action| action.name().len())
+        .max()
+        .unwrap_or(0);
+
+    keymap
+        .bindings()
+        .map(|(action, keys)| {
+            let keys: Vec<String> = keys.iter().map(ToString::to_string).collect();
+            Line::from(vec![
+                format!(" {:width$}  ", action.name()).bold(),
+                format!("{} ", keys.join(", ")).into(),
+            ])
+        })
+        .collect()
 }
 
 impl Default for CliFrontEnd {
     fn default() -> Self {
         let terminal = ratatui::init();
+        // Without mouse capture the game is still fully playable by keyboard
+        let _ = execute!(std::io::stdout(), EnableMouseCapture);
 
-        CliFrontEnd { terminal }
+        CliFrontEnd {
+            terminal,
+            title: String::from("Sokoban"),
+            keymap: Keymap::default(),
+            show_help: false,
+            redraw: false,
+            zoom: Zoom::default(),
+            theme: Theme::default(),
+            overlays: Overlays::default(),
+            selected_box: None,
+            menu: None,
+            hint: None,
+            boxes: Vec::new(),
+            scroll: (0, 0),
+            viewport: None,
+            drag_start: None,
+        }
     }
 }
 
 impl Drop for CliFrontEnd {
     fn drop(&mut self) {
+        let _ = execute!(std::io::stdout(), DisableMouseCapture);
         ratatui::restore();
     }
 }
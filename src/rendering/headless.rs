@@ -0,0 +1,92 @@
+use std::{collections::VecDeque, time::Duration};
+
+use crate::{
+    game::{GameState, stats::Stats},
+    input::InputEvent,
+    rendering::{FrontEnd, FrontEndEvent, GameEvent},
+};
+
+/// A front end without a terminal, for tests and scripts.
+///
+/// Every call to `render` records the board as an XSB string, game events
+/// are kept in order, and input is taken from a queue of scripted events.
+/// Once the script runs out the front end reports no input for `idle_limit`
+/// polls and then answers `Quit`, so a game driven by it always comes to an
+/// end.
+#[derive(Debug, Clone, Default)]
+pub struct HeadlessFrontEnd {
+    inputs: VecDeque<InputEvent>,
+    frames: Vec<String>,
+    events: Vec<GameEvent>,
+    idle_limit: usize,
+    idle_polls: usize,
+}
+
+impl HeadlessFrontEnd {
+    pub fn new(inputs: impl IntoIterator<Item = InputEvent>) -> Self {
+        HeadlessFrontEnd {
+            inputs: inputs.into_iter().collect(),
+            ..Self::default()
+        }
+    }
+
+    /// How many polls without input to answer once the script is used up
+    /// before quitting. With a zero `autoplay_delay`, autoplay makes one
+    /// move per poll, so it needs at least as many as the solution has moves
+    /// to finish, plus one per tick the solver takes to find it.
+    pub fn with_idle_limit(mut self, idle_limit: usize) -> Self {
+        self.idle_limit = idle_limit;
+        self
+    }
+
+    /// Appends events to the end of the script.
+    pub fn push_inputs(&mut self, inputs: impl IntoIterator<Item = InputEvent>) {
+        self.inputs.extend(inputs);
+    }
+
+    /// The events that have not been read yet.
+    pub fn remaining_inputs(&self) -> &VecDeque<InputEvent> {
+        &self.inputs
+    }
+
+    /// Every distinct frame rendered so far, oldest first. A render that
+    /// looks the same as the one before it is not recorded again.
+    pub fn frames(&self) -> &[String] {
+        &self.frames
+    }
+
+    /// Every game event received so far, oldest first, ticks included.
+    pub fn events(&self) -> &[GameEvent] {
+        &self.events
+    }
+
+    pub fn last_frame(&self) -> Option<&str> {
+        self.frames.last().map(String::as_str)
+    }
+}
+
+impl FrontEnd for HeadlessFrontEnd {
+    fn render(&mut self, state: &GameState, _stats: &Stats) {
+        let frame = state.to_xsb();
+        if self.last_frame() != Some(frame.as_str()) {
+            self.frames.push(frame);
+        }
+    }
+
+    /// Never waits: the timeout is ignored so scripted games run as fast as
+    /// possible.
+    fn poll_event(&mut self, _timeout: Duration) -> Option<FrontEndEvent> {
+        if let Some(input) = self.inputs.pop_front() {
+            return Some(FrontEndEvent::Input(input));
+        }
+        if self.idle_polls < self.idle_limit {
+            self.idle_polls += 1;
+            return None;
+        }
+        Some(FrontEndEvent::Input(InputEvent::Quit))
+    }
+
+    fn game_event(&mut self, event: &GameEvent) {
+        self.events.push(event.clone());
+    }
+}
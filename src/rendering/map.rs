@@ -0,0 +1,369 @@
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+};
+
+use crate::{
+    game::GameState,
+    rendering::{overlay::Layers, theme::Theme},
+};
+
+/// What a single map cell shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tile {
+    Wall,
+    Floor,
+    Target,
+    Box,
+    BoxOnTarget,
+    Player,
+    /// A cell on the suggested route of a box.
+    Route,
+    /// A floor cell a box can never be pushed off towards a target.
+    Dead,
+    /// A floor cell the player can walk to.
+    Reachable,
+    /// A cell the selected box can be pushed to.
+    PushReachable,
+    /// A box that can never move again.
+    Frozen,
+    /// The box a hint says to push next.
+    HintBox,
+}
+
+impl Tile {
+    /// What to draw at `pos`, with the cells marked in `layers` shown
+    /// where nothing more important is.
+    pub fn at(state: &GameState, layers: &Layers, pos: (i32, i32)) -> Self {
+        if pos == state.player_position {
+            Tile::Player
+        } else if state.box_positions.contains(&pos) {
+            if layers.hint_box == Some(pos) {
+                Tile::HintBox
+            } else if layers.frozen.contains(&pos) {
+                Tile::Frozen
+            } else if state.target_positions.contains(&pos) {
+                Tile::BoxOnTarget
+            } else {
+                Tile::Box
+            }
+        } else if state.target_positions.contains(&pos) {
+            Tile::Target
+        } else if state.walls.contains(&pos) {
+            Tile::Wall
+        } else if layers.hint.contains(&pos) {
+            Tile::Route
+        } else if layers.push_reachable.contains(&pos) {
+            Tile::PushReachable
+        } else if layers.dead.contains(&pos) {
+            Tile::Dead
+        } else if layers.reachable.contains(&pos) {
+            Tile::Reachable
+        } else {
+            Tile::Floor
+        }
+    }
+}
+
+/// Which of the four cells next to a wall are walls too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WallJoins {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+}
+
+impl WallJoins {
+    pub fn at(state: &GameState, (r, c): (i32, i32)) -> Self {
+        let wall = |pos| state.walls.contains(&pos);
+        WallJoins {
+            up: wall((r - 1, c)),
+            down: wall((r + 1, c)),
+            left: wall((r, c - 1)),
+            right: wall((r, c + 1)),
+        }
+    }
+
+    /// The box-drawing character with a line towards each joined side. A
+    /// wall joined on one side or none is drawn as a straight line.
+    pub fn glyph(self) -> char {
+        let WallJoins {
+            up,
+            down,
+            left,
+            right,
+        } = self;
+        match (up, down, left, right) {
+            (true, true, true, true) => '┼',
+            (true, true, true, false) => '┤',
+            (true, true, false, true) => '├',
+            (true, false, true, true) => '┴',
+            (false, true, true, true) => '┬',
+            (false, true, false, true) => '┌',
+            (false, true, true, false) => '┐',
+            (true, false, false, true) => '└',
+            (true, false, true, false) => '┘',
+            (_, _, false, false) if up || down => '│',
+            _ => '─',
+        }
+    }
+}
+
+/// How many terminal cells a map cell takes up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Zoom {
+    /// `Wide` if the whole map fits that way, `Normal` otherwise.
+    #[default]
+    Auto,
+    /// Two map rows per terminal line, drawn with half blocks.
+    Compact,
+    /// One terminal cell per map cell.
+    Normal,
+    /// Two terminal columns per map cell.
+    Wide,
+}
+
+impl Zoom {
+    /// The zoom to switch to next when cycling through them.
+    pub fn next(self) -> Self {
+        match self {
+            Zoom::Auto => Zoom::Compact,
+            Zoom::Compact => Zoom::Normal,
+            Zoom::Normal => Zoom::Wide,
+            Zoom::Wide => Zoom::Auto,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Zoom::Auto => "auto",
+            Zoom::Compact => "compact",
+            Zoom::Normal => "normal",
+            Zoom::Wide => "wide",
+        }
+    }
+
+    fn resolve(self, (rows, cols): (i32, i32), area: Rect) -> Self {
+        match self {
+            Zoom::Auto if cols * 2 <= area.width as i32 && rows <= area.height as i32 => Zoom::Wide,
+            Zoom::Auto => Zoom::Normal,
+            zoom => zoom,
+        }
+    }
+
+    fn cell_width(self, theme: &Theme) -> i32 {
+        match self {
+            Zoom::Wide => 2,
+            Zoom::Compact => 1,
+            _ => theme.cell_width(),
+        }
+    }
+
+    fn rows_per_line(self) -> i32 {
+        if self == Zoom::Compact { 2 } else { 1 }
+    }
+}
+
+/// The part of the map that is on screen and where it is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    /// The screen area the visible cells are drawn in.
+    pub area: Rect,
+    /// Never `Zoom::Auto`.
+    pub zoom: Zoom,
+    /// The map cell shown in the top left corner.
+    pub scroll: (i32, i32),
+    /// How many map rows and columns are visible.
+    pub size: (i32, i32),
+    /// How many terminal columns a map cell takes up.
+    pub cell_width: i32,
+}
+
+impl Viewport {
+    /// Fits the map into `area`. When it does not fit, the view scrolls
+    /// from `scroll` just far enough to keep the player away from the edges.
+    /// Returns `None` if not even one cell fits.
+    pub fn new(
+        state: &GameState,
+        theme: &Theme,
+        area: Rect,
+        zoom: Zoom,
+        scroll: (i32, i32),
+    ) -> Option<Self> {
+        let (map_rows, map_cols) = state.map_size;
+        let zoom = zoom.resolve(state.map_size, area);
+        let cell_width = zoom.cell_width(theme);
+
+        let rows = map_rows.min(area.height as i32 * zoom.rows_per_line());
+        let cols = map_cols.min(area.width as i32 / cell_width);
+        if rows <= 0 || cols <= 0 {
+            return None;
+        }
+
+        let (player_row, player_col) = state.player_position;
+        let scroll = (
+            follow(scroll.0, player_row, rows, map_rows),
+            follow(scroll.1, player_col, cols, map_cols),
+        );
+
+        let width = (cols * cell_width) as u16;
+        let height = ((rows + zoom.rows_per_line() - 1) / zoom.rows_per_line()) as u16;
+        let area = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+
+        Some(Viewport {
+            area,
+            zoom,
+            scroll,
+            size: (rows, cols),
+            cell_width,
+        })
+    }
+
+    pub fn lines(&self, state: &GameState, theme: &Theme, layers: &Layers) -> Vec<Line<'static>> {
+        let (rows, cols) = self.size;
+        let (top, left) = self.scroll;
+        let columns = left..left + cols;
+
+        match self.zoom {
+            Zoom::Compact => (0..rows)
+                .step_by(2)
+                .map(|offset| {
+                    let upper = top + offset;
+                    let lower = upper + 1;
+                    let spans: Vec<Span> = columns
+                        .clone()
+                        .map(|c| {
+                            let background = if offset + 1 < rows {
+                                theme.block(Tile::at(state, layers, (lower, c)))
+                            } else {
+                                Color::Reset
+                            };
+                            let foreground = theme.block(Tile::at(state, layers, (upper, c)));
+                            Span::styled("▀", Style::new().fg(foreground).bg(background))
+                        })
+                        .collect();
+                    Line::from(spans)
+                })
+                .collect(),
+            zoom => (top..top + rows)
+                .map(|r| {
+                    let spans: Vec<Span> = columns
+                        .clone()
+                        .map(|c| {
+                            let tile = Tile::at(state, layers, (r, c));
+                            if tile == Tile::Wall && theme.joined_walls {
+                                theme.joined_wall_span(
+                                    WallJoins::at(state, (r, c)),
+                                    self.cell_width == 2,
+                                )
+                            } else if zoom == Zoom::Wide {
+                                theme.wide_span(tile)
+                            } else {
+                                theme.span(tile)
+                            }
+                        })
+                        .collect();
+                    Line::from(spans)
+                })
+                .collect(),
+        }
+    }
+
+    /// The map cell under a terminal position, if it is on the map. In
+    /// compact mode a terminal line covers two rows and the upper one wins.
+    pub fn cell_at(&self, column: u16, row: u16) -> Option<(i32, i32)> {
+        let area = self.area;
+        let inside = column >= area.x
+            && column < area.x + area.width
+            && row >= area.y
+            && row < area.y + area.height;
+        if !inside {
+            return None;
+        }
+
+        let r = (row - area.y) as i32 * self.zoom.rows_per_line() + self.scroll.0;
+        let c = (column - area.x) as i32 / self.cell_width + self.scroll.1;
+        Some((r, c))
+    }
+}
+
+/// The new scroll offset along one axis, keeping `player` at least a few
+/// cells from the edge of a window of `visible` cells where possible.
+fn follow(scroll: i32, player: i32, visible: i32, len: i32) -> i32 {
+    if visible >= len {
+        return 0;
+    }
+
+    let margin = (visible / 4).min(3);
+    let lowest = player - visible + 1 + margin;
+    let highest = player - margin;
+    scroll.clamp(lowest, highest).clamp(0, len - visible)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::testing::xsb;
+
+    #[test]
+    fn walls_join_their_neighbours() {
+        let state = xsb("#####\n#@$.#\n## ##\n #  #\n ####");
+        let glyphs: Vec<String> = (0..state.map_size.0)
+            .map(|r| {
+                (0..state.map_size.1)
+                    .map(|c| {
+                        if state.walls.contains(&(r, c)) {
+                            WallJoins::at(&state, (r, c)).glyph()
+                        } else {
+                            ' '
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        assert_eq!(glyphs, ["┌───┐", "│   │", "└┐ ─┤", " │  │", " └──┘"]);
+    }
+
+    #[test]
+    fn follow_keeps_the_player_away_from_the_edges() {
+        assert_eq!(follow(5, 3, 10, 10), 0); // Everything fits
+        assert_eq!(follow(0, 1, 10, 40), 0);
+        assert_eq!(follow(0, 35, 10, 40), 28);
+        assert_eq!(follow(28, 30, 10, 40), 28); // Still inside the margins
+        assert_eq!(follow(28, 29, 10, 40), 27);
+        assert_eq!(follow(0, 38, 10, 40), 30); // Never past the end
+    }
+
+    #[test]
+    fn cell_at_maps_the_screen_back_to_the_map() {
+        let mut row = String::from("#@");
+        row.push_str(&" ".repeat(36));
+        let state = xsb(&format!("{0}\n{row}$.#\n{0}", "#".repeat(41)));
+        let theme = Theme::default();
+        let area = Rect::new(2, 1, 10, 3);
+
+        let normal = Viewport::new(&state, &theme, area, Zoom::Normal, (0, 30)).unwrap();
+        assert_eq!(normal.scroll, (0, 0));
+        assert_eq!(normal.size, (3, 10));
+        assert_eq!(normal.cell_at(2, 1), Some((0, 0)));
+        assert_eq!(normal.cell_at(5, 2), Some((1, 3)));
+        assert_eq!(normal.cell_at(12, 2), None);
+        assert_eq!(normal.cell_at(1, 2), None);
+
+        let wide = Viewport::new(&state, &theme, area, Zoom::Wide, (0, 0)).unwrap();
+        assert_eq!(wide.size, (3, 5));
+        assert_eq!(wide.cell_at(5, 2), Some((1, 1)));
+
+        let compact = Viewport::new(&state, &theme, area, Zoom::Compact, (0, 0)).unwrap();
+        assert_eq!(compact.area.height, 2);
+        assert_eq!(compact.cell_at(2, 2), Some((2, 0)));
+    }
+}
@@ -0,0 +1,365 @@
+use ratatui::{
+    Frame,
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style, Stylize},
+    symbols::border,
+    text::Line,
+    widgets::{Block, Clear, List, ListItem, ListState, Paragraph},
+};
+
+use crate::{
+    game::stats::{LevelInfo, format_duration},
+    rendering::{
+        map::{Viewport, Zoom},
+        overlay::Layers,
+        theme::Theme,
+    },
+};
+
+/// How many levels Page Up and Page Down skip.
+const PAGE: usize = 10;
+
+/// Which level sizes the menu lists, by the longer side of the map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SizeFilter {
+    #[default]
+    Any,
+    /// Up to 10 cells.
+    Small,
+    /// 11 to 15 cells.
+    Medium,
+    /// 16 cells or more.
+    Large,
+}
+
+impl SizeFilter {
+    pub fn next(self) -> Self {
+        match self {
+            SizeFilter::Any => SizeFilter::Small,
+            SizeFilter::Small => SizeFilter::Medium,
+            SizeFilter::Medium => SizeFilter::Large,
+            SizeFilter::Large => SizeFilter::Any,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SizeFilter::Any => "any",
+            SizeFilter::Small => "small",
+            SizeFilter::Medium => "medium",
+            SizeFilter::Large => "large",
+        }
+    }
+
+    pub fn matches(self, (rows, cols): (i32, i32)) -> bool {
+        let side = rows.max(cols);
+        match self {
+            SizeFilter::Any => true,
+            SizeFilter::Small => side <= 10,
+            SizeFilter::Medium => (11..=15).contains(&side),
+            SizeFilter::Large => side >= 16,
+        }
+    }
+}
+
+/// What the front end should do after a key press in the menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuAction {
+    /// The menu changed and has to be drawn again.
+    Redraw,
+    /// Go back to the level being played.
+    Close,
+    /// Play the level with this 1-based number.
+    Play(usize),
+    Quit,
+}
+
+/// A list of the levels of a collection to pick one from, with a preview
+/// of the selected level.
+///
+/// The menu reads keys directly instead of going through the keymap, as
+/// typing filters the list by name: arrows, Page Up/Down, Home and End move
+/// the selection, Enter plays the selected level, Tab cycles the size
+/// filter, Esc clears the filter or closes the menu and Ctrl+C quits.
+#[derive(Debug, Clone)]
+pub struct LevelMenu {
+    levels: Vec<LevelInfo>,
+    filter: String,
+    size: SizeFilter,
+    /// The number of the selected level.
+    selected: usize,
+}
+
+impl LevelMenu {
+    /// Opens the menu with the level numbered `current` selected.
+    pub fn new(levels: Vec<LevelInfo>, current: usize) -> Self {
+        LevelMenu {
+            levels,
+            filter: String::new(),
+            size: SizeFilter::Any,
+            selected: current,
+        }
+    }
+
+    /// The levels that pass the filters: the name or number has to contain
+    /// the filter text, ignoring case, and the size has to match.
+    pub fn visible(&self) -> Vec<&LevelInfo> {
+        let filter = self.filter.to_lowercase();
+        self.levels
+            .iter()
+            .filter(|level| {
+                level.name.to_lowercase().contains(&filter)
+                    || level.number.to_string().contains(&filter)
+            })
+            .filter(|level| self.size.matches(level.size))
+            .collect()
+    }
+
+    /// Where the selected level is among the visible ones, or the first
+    /// visible level if the filters hide it.
+    fn selected_index(&self, visible: &[&LevelInfo]) -> Option<usize> {
+        if visible.is_empty() {
+            return None;
+        }
+        let index = visible
+            .iter()
+            .position(|level| level.number == self.selected);
+        Some(index.unwrap_or(0))
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> MenuAction {
+        let visible = self.visible();
+        let index = self.selected_index(&visible);
+        let last = visible.len().saturating_sub(1);
+        let select = |index: usize| visible.get(index).map(|level| level.number);
+
+        let selected = match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return MenuAction::Quit;
+            }
+            KeyCode::Enter => {
+                return match index.and_then(select) {
+                    Some(number) => MenuAction::Play(number),
+                    None => MenuAction::Redraw,
+                };
+            }
+            KeyCode::Esc if self.filter.is_empty() => return MenuAction::Close,
+            KeyCode::Up => index.and_then(|index| select(index.saturating_sub(1))),
+            KeyCode::Down => index.and_then(|index| select((index + 1).min(last))),
+            KeyCode::PageUp => index.and_then(|index| select(index.saturating_sub(PAGE))),
+            KeyCode::PageDown => index.and_then(|index| select((index + PAGE).min(last))),
+            KeyCode::Home => select(0),
+            KeyCode::End => select(last),
+            _ => None,
+        };
+        if let Some(number) = selected {
+            self.selected = number;
+            return MenuAction::Redraw;
+        }
+
+        match key.code {
+            KeyCode::Esc => self.filter.clear(),
+            KeyCode::Tab => self.size = self.size.next(),
+            KeyCode::Backspace => {
+                self.filter.pop();
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.filter.push(c);
+            }
+            _ => {}
+        }
+        MenuAction::Redraw
+    }
+
+    pub fn render(&self, frame: &mut Frame, theme: &Theme) {
+        let area = frame.area();
+        let block = Block::bordered()
+            .title(Line::raw("Sokoban - Levels").centered())
+            .title_bottom(
+                Line::raw("Type to filter, 'tab' for size, 'enter' to play, 'esc' to go back")
+                    .centered(),
+            )
+            .border_set(border::THICK);
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let [filter_area, body] =
+            Layout::vertical([Constraint::Length(2), Constraint::Min(0)]).areas(inner);
+        let solved = self.levels.iter().filter(|level| level.is_solved()).count();
+        let filter = Line::from(vec![
+            " Filter: ".bold(),
+            format!("{}_", self.filter).into(),
+            "   Size: ".bold(),
+            self.size.name().into(),
+            format!("   {solved}/{} solved", self.levels.len()).dim(),
+        ]);
+        frame.render_widget(Paragraph::new(filter), filter_area);
+
+        let [list_area, preview_area] =
+            Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)])
+                .areas(body);
+
+        let visible = self.visible();
+        let index = self.selected_index(&visible);
+        let items: Vec<ListItem> = visible
+            .iter()
+            .map(|level| {
+                let status = if level.is_solved() { "✓" } else { " " };
+                let best = level.best.map_or_else(
+                    || String::from("-"),
+                    |best| format!("{}/{}", best.moves, best.pushes),
+                );
+                let (rows, cols) = level.size;
+                ListItem::new(Line::raw(format!(
+                    "{status} {:>3}  {:<24} {:>5}  {best}",
+                    level.number,
+                    truncate(&level.name, 24),
+                    format!("{cols}x{rows}"),
+                )))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::bordered().title(Line::raw(format!(
+                " {} of {} levels ",
+                visible.len(),
+                self.levels.len()
+            ))))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+        let mut state = ListState::default().with_selected(index);
+        frame.render_stateful_widget(list, list_area, &mut state);
+
+        let preview = Block::bordered().title(Line::raw(" Preview ").centered());
+        let preview_inner = preview.inner(preview_area);
+        frame.render_widget(preview, preview_area);
+        if let Some(level) = index.and_then(|index| visible.get(index)) {
+            render_preview(frame, preview_inner, level, theme);
+        }
+    }
+}
+
+/// Draws a level's starting position small enough to fit, with its details
+/// below it.
+fn render_preview(frame: &mut Frame, area: Rect, level: &LevelInfo, theme: &Theme) {
+    let (rows, cols) = level.size;
+    let details = vec![
+        Line::from(level.name.clone().bold()),
+        Line::raw(format!("{cols}x{rows}, {} boxes", level.boxes)),
+        Line::raw(match level.best {
+            Some(best) => format!("Best: {best}"),
+            None => String::from("Not solved yet"),
+        }),
+        Line::raw(level.best_time.map_or_else(String::new, |time| {
+            format!("Fastest: {}", format_duration(time))
+        })),
+    ];
+    let [board_area, details_area] =
+        Layout::vertical([Constraint::Min(0), Constraint::Length(details.len() as u16)])
+            .areas(area);
+    frame.render_widget(Paragraph::new(details).centered(), details_area);
+
+    let state = &level.state;
+    let fits =
+        rows <= board_area.height as i32 && cols * theme.cell_width() <= board_area.width as i32;
+    let zoom = if fits { Zoom::Normal } else { Zoom::Compact };
+    if let Some(viewport) = Viewport::new(state, theme, board_area, zoom, (0, 0)) {
+        let lines = viewport.lines(state, theme, &Layers::default());
+        frame.render_widget(Paragraph::new(lines), viewport.area);
+    }
+}
+
+/// Cuts `text` to at most `width` characters, marking the cut with `…`.
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(width - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::testing::{CORRIDOR, xsb};
+
+    fn menu() -> LevelMenu {
+        let levels = [
+            ("Corridor", (3, 5)),
+            ("Warehouse", (12, 14)),
+            ("Big Hall", (20, 30)),
+            ("Small Hall", (8, 9)),
+        ];
+        let levels = levels
+            .into_iter()
+            .enumerate()
+            .map(|(index, (name, size))| LevelInfo {
+                number: index + 1,
+                name: name.to_string(),
+                size,
+                boxes: 1,
+                best: None,
+                best_time: None,
+                state: xsb(CORRIDOR),
+            })
+            .collect();
+        LevelMenu::new(levels, 1)
+    }
+
+    fn press(menu: &mut LevelMenu, code: KeyCode) -> MenuAction {
+        menu.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn type_text(menu: &mut LevelMenu, text: &str) {
+        for c in text.chars() {
+            press(menu, KeyCode::Char(c));
+        }
+    }
+
+    fn numbers(menu: &LevelMenu) -> Vec<usize> {
+        menu.visible().iter().map(|level| level.number).collect()
+    }
+
+    #[test]
+    fn visible_filters_by_name_number_and_size() {
+        let mut menu = menu();
+        assert_eq!(numbers(&menu), [1, 2, 3, 4]);
+
+        type_text(&mut menu, "HALL");
+        assert_eq!(numbers(&menu), [3, 4]);
+
+        press(&mut menu, KeyCode::Tab); // Small
+        assert_eq!(numbers(&menu), [4]);
+        press(&mut menu, KeyCode::Tab); // Medium
+        assert!(numbers(&menu).is_empty());
+
+        press(&mut menu, KeyCode::Esc);
+        assert_eq!(numbers(&menu), [2]);
+        press(&mut menu, KeyCode::Tab); // Large
+        type_text(&mut menu, "3");
+        assert_eq!(numbers(&menu), [3]);
+    }
+
+    #[test]
+    fn keys_move_the_selection_among_visible_levels() {
+        let mut menu = menu();
+        press(&mut menu, KeyCode::End);
+        assert_eq!(press(&mut menu, KeyCode::Enter), MenuAction::Play(4));
+
+        type_text(&mut menu, "a");
+        assert_eq!(numbers(&menu), [2, 3, 4]);
+        press(&mut menu, KeyCode::Home);
+        press(&mut menu, KeyCode::Down);
+        assert_eq!(press(&mut menu, KeyCode::Enter), MenuAction::Play(3));
+
+        // Esc clears the filter first, then closes the menu
+        assert_eq!(press(&mut menu, KeyCode::Esc), MenuAction::Redraw);
+        assert_eq!(press(&mut menu, KeyCode::Esc), MenuAction::Close);
+        assert_eq!(
+            menu.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            MenuAction::Quit
+        );
+    }
+}
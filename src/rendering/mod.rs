@@ -1,8 +1,80 @@
-use crate::{game::GameState, input::InputEvent};
+use std::time::Duration;
+
+use crate::{
+    game::{
+        GameState,
+        deadlock::DeadlockKind,
+        hint::Hint,
+        history::Move,
+        solver::SolveError,
+        stats::{LevelInfo, Stats},
+    },
+    input::InputEvent,
+};
 
 pub mod cli;
+pub mod headless;
+pub mod map;
+pub mod menu;
+pub mod overlay;
+pub mod theme;
+
+/// Something that happened on the front end's side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrontEndEvent {
+    Input(InputEvent),
+    /// The drawing area changed size.
+    Resize {
+        width: u16,
+        height: u16,
+    },
+}
+
+/// Something that happened in the game that a front end may want to show.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameEvent {
+    /// A level was started, either at launch or by moving to the next one.
+    LevelLoaded { number: usize, name: String },
+    /// The player made a move, by hand, by redo or in autoplay.
+    Moved(Move),
+    /// The given move was taken back.
+    Undone(Move),
+    /// The level went back to its starting position.
+    Restarted,
+    /// Every box is on a target now.
+    Solved,
+    /// The position can no longer be solved.
+    Deadlocked(DeadlockKind),
+    /// The player asked for a hint: the next push, or why there is none.
+    Hint(Result<Hint, SolveError>),
+    /// The player wants to pick a level. `current` is the number of the
+    /// one being played.
+    ShowLevels {
+        levels: Vec<LevelInfo>,
+        current: usize,
+    },
+    /// Time has passed, for animations. Holds the time since the last tick.
+    Tick(Duration),
+}
 
+/// How the game talks to whatever shows it.
+///
+/// The game never sleeps. It asks the front end for events with a timeout
+/// that runs until its next tick or autoplay step, tells it about
+/// everything that happens through `game_event`, and calls `render` whenever
+/// the state changed or the front end asks for it with `needs_redraw`.
 pub trait FrontEnd: Default {
-    fn render(&mut self, state: &GameState);
-    fn get_input(&self) -> Option<InputEvent>;
+    fn render(&mut self, state: &GameState, stats: &Stats);
+
+    /// Waits at most `timeout` for the next event. A zero timeout only looks
+    /// at events that are already pending.
+    fn poll_event(&mut self, timeout: Duration) -> Option<FrontEndEvent>;
+
+    fn game_event(&mut self, _event: &GameEvent) {}
+
+    /// Whether the front end wants to draw again although the state did not
+    /// change, e.g. while an animation is running.
+    fn needs_redraw(&self) -> bool {
+        false
+    }
 }
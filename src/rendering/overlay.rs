@@ -0,0 +1,129 @@
+use std::collections::HashSet;
+
+use crate::{
+    game::{GameState, hint::Hint, squares::SquareKind},
+    rendering::map::Tile,
+};
+
+/// An analysis layer that can be drawn over the map. All of them are off
+/// by default, so they do not give the puzzle away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Overlay {
+    /// Floor a box can never be pushed to a target from.
+    DeadSquares,
+    /// Floor the player can walk to without pushing.
+    Reachable,
+    /// Cells the selected box can be pushed to without moving other boxes.
+    PushReachable,
+    /// The push suggested by the last hint: the box, the player's path to
+    /// it and the box's next cell.
+    HintPath,
+    /// Boxes that can never move again.
+    Frozen,
+}
+
+impl Overlay {
+    pub const ALL: [Overlay; 5] = [
+        Overlay::DeadSquares,
+        Overlay::Reachable,
+        Overlay::PushReachable,
+        Overlay::HintPath,
+        Overlay::Frozen,
+    ];
+
+    /// What the overlay is called in the legend.
+    pub fn name(self) -> &'static str {
+        match self {
+            Overlay::DeadSquares => "dead squares",
+            Overlay::Reachable => "reachable",
+            Overlay::PushReachable => "box can go",
+            Overlay::HintPath => "hint path",
+            Overlay::Frozen => "frozen boxes",
+        }
+    }
+
+    /// The tile the overlay marks cells with.
+    pub fn tile(self) -> Tile {
+        match self {
+            Overlay::DeadSquares => Tile::Dead,
+            Overlay::Reachable => Tile::Reachable,
+            Overlay::PushReachable => Tile::PushReachable,
+            Overlay::HintPath => Tile::Route,
+            Overlay::Frozen => Tile::Frozen,
+        }
+    }
+}
+
+/// Which overlays are switched on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Overlays {
+    enabled: [bool; Overlay::ALL.len()],
+}
+
+impl Overlays {
+    pub fn is_on(&self, overlay: Overlay) -> bool {
+        self.enabled[overlay as usize]
+    }
+
+    pub fn set(&mut self, overlay: Overlay, on: bool) {
+        self.enabled[overlay as usize] = on;
+    }
+
+    pub fn toggle(&mut self, overlay: Overlay) {
+        self.set(overlay, !self.is_on(overlay));
+    }
+
+    pub fn any(&self) -> bool {
+        self.enabled.contains(&true)
+    }
+}
+
+/// The cells marked by each switched-on overlay in one position.
+#[derive(Debug, Clone, Default)]
+pub struct Layers {
+    pub dead: HashSet<(i32, i32)>,
+    pub reachable: HashSet<(i32, i32)>,
+    pub push_reachable: HashSet<(i32, i32)>,
+    pub hint: HashSet<(i32, i32)>,
+    pub frozen: HashSet<(i32, i32)>,
+    /// The box a hint says to push next.
+    pub hint_box: Option<(i32, i32)>,
+}
+
+impl Layers {
+    /// Works out the switched-on overlays. `selected` is the index of the
+    /// box whose push-reachable cells are shown, and `hint` the answer to the
+    /// last request for a hint.
+    pub fn new(
+        state: &GameState,
+        overlays: Overlays,
+        selected: Option<usize>,
+        hint: Option<&Hint>,
+    ) -> Self {
+        let mut layers = Layers::default();
+
+        if overlays.is_on(Overlay::DeadSquares) {
+            layers.dead = state.squares.positions(SquareKind::Dead).collect();
+        }
+        if overlays.is_on(Overlay::Reachable) {
+            layers.reachable = state.reachable_cells();
+        }
+        if overlays.is_on(Overlay::PushReachable)
+            && let Some(&from) = selected.and_then(|index| state.box_positions.get(index))
+        {
+            layers.push_reachable = state.push_reachable(from);
+        }
+        if overlays.is_on(Overlay::HintPath)
+            && let Some(hint) = hint
+        {
+            layers.hint.extend(hint.path.iter().copied());
+            layers.hint.insert(hint.destination());
+            layers.hint_box = Some(hint.push.box_position);
+        }
+        if overlays.is_on(Overlay::Frozen) {
+            layers.frozen = state.frozen_boxes().into_iter().collect();
+        }
+
+        layers
+    }
+}
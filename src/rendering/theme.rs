@@ -0,0 +1,534 @@
+use std::{fmt, path::Path, str::FromStr};
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::Span,
+};
+use serde::Deserialize;
+
+use crate::rendering::map::{Tile, WallJoins};
+
+/// How one kind of tile is drawn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TileStyle {
+    pub symbol: String,
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    /// The colour of the tile in compact mode, where there is no room for a
+    /// symbol.
+    pub block: Color,
+}
+
+impl TileStyle {
+    fn new(symbol: &str, fg: Option<Color>, bg: Option<Color>, block: Color) -> Self {
+        TileStyle {
+            symbol: symbol.to_string(),
+            fg,
+            bg,
+            bold: false,
+            block,
+        }
+    }
+
+    fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    fn style(&self) -> Style {
+        let mut style = Style::new();
+        if let Some(fg) = self.fg {
+            style = style.fg(fg);
+        }
+        if let Some(bg) = self.bg {
+            style = style.bg(bg);
+        }
+        if self.bold {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        style
+    }
+}
+
+/// Glyphs and colours for every tile.
+///
+/// Built-in themes are listed in `Theme::NAMES`. Theme files are TOML and
+/// start from a built-in theme, `classic` unless `base` says otherwise:
+///
+/// ```toml
+/// base = "unicode"
+/// name = "mine"
+///
+/// [player]
+/// symbol = "@"
+/// fg = "#ff8800"
+/// bold = true
+/// ```
+///
+/// Setting `joined_walls = true` draws walls as box-drawing lines joined to
+/// their neighbours instead of with the wall symbol, which keeps its colours.
+///
+/// Tiles are `wall`, `floor`, `target`, `box`, `box_on_target`, `player`,
+/// and the overlay tiles `route`, `dead`, `reachable`, `push_reachable`,
+/// `frozen` and `hint_box`. Colours are names like `red` or `lightblue`, hex
+/// codes or 256-colour indices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    pub name: String,
+    /// Whether every symbol is two columns wide, like emoji. Such themes
+    /// look the same in normal and wide zoom.
+    pub wide: bool,
+    /// Whether walls are drawn as lines joined to the neighbouring walls.
+    pub joined_walls: bool,
+    pub wall: TileStyle,
+    pub floor: TileStyle,
+    pub target: TileStyle,
+    pub box_: TileStyle,
+    pub box_on_target: TileStyle,
+    pub player: TileStyle,
+    pub route: TileStyle,
+    pub dead: TileStyle,
+    pub reachable: TileStyle,
+    pub push_reachable: TileStyle,
+    pub frozen: TileStyle,
+    pub hint_box: TileStyle,
+}
+
+#[derive(Debug)]
+pub enum ThemeError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    UnknownTheme(String),
+    InvalidColor(String),
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThemeError::Io(err) => write!(f, "failed to read theme: {err}"),
+            ThemeError::Parse(err) => write!(f, "invalid theme: {err}"),
+            ThemeError::UnknownTheme(name) => write!(
+                f,
+                "unknown theme {name:?}, expected one of {} or a theme file",
+                Theme::NAMES.join(", ")
+            ),
+            ThemeError::InvalidColor(color) => write!(f, "invalid color {color:?}"),
+        }
+    }
+}
+
+impl std::error::Error for ThemeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ThemeError::Io(err) => Some(err),
+            ThemeError::Parse(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ThemeError {
+    fn from(err: std::io::Error) -> Self {
+        ThemeError::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for ThemeError {
+    fn from(err: toml::de::Error) -> Self {
+        ThemeError::Parse(err)
+    }
+}
+
+/// A theme file as written, before it is merged onto its base.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    base: Option<String>,
+    name: Option<String>,
+    wide: Option<bool>,
+    joined_walls: Option<bool>,
+    wall: Option<TileFile>,
+    floor: Option<TileFile>,
+    target: Option<TileFile>,
+    #[serde(rename = "box")]
+    box_: Option<TileFile>,
+    box_on_target: Option<TileFile>,
+    player: Option<TileFile>,
+    route: Option<TileFile>,
+    dead: Option<TileFile>,
+    reachable: Option<TileFile>,
+    push_reachable: Option<TileFile>,
+    frozen: Option<TileFile>,
+    hint_box: Option<TileFile>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TileFile {
+    symbol: Option<String>,
+    fg: Option<String>,
+    bg: Option<String>,
+    bold: Option<bool>,
+    block: Option<String>,
+}
+
+impl TileFile {
+    fn merge_into(self, tile: &mut TileStyle) -> Result<(), ThemeError> {
+        let color =
+            |color: String| Color::from_str(&color).map_err(|_| ThemeError::InvalidColor(color));
+
+        if let Some(symbol) = self.symbol {
+            tile.symbol = symbol;
+        }
+        if let Some(fg) = self.fg {
+            tile.fg = Some(color(fg)?);
+        }
+        if let Some(bg) = self.bg {
+            tile.bg = Some(color(bg)?);
+        }
+        if let Some(bold) = self.bold {
+            tile.bold = bold;
+        }
+        if let Some(block) = self.block {
+            tile.block = color(block)?;
+        }
+        Ok(())
+    }
+}
+
+impl Theme {
+    /// The names of the built-in themes, in the order they are cycled.
+    pub const NAMES: [&str; 5] = ["classic", "unicode", "emoji", "high-contrast", "colorblind"];
+
+    /// The built-in theme with the given name.
+    pub fn named(name: &str) -> Option<Theme> {
+        match name {
+            "classic" => Some(Theme::classic()),
+            "unicode" => Some(Theme::unicode()),
+            "emoji" => Some(Theme::emoji()),
+            "high-contrast" => Some(Theme::high_contrast()),
+            "colorblind" => Some(Theme::colorblind()),
+            _ => None,
+        }
+    }
+
+    /// A built-in theme by name, or else a theme file at that path.
+    pub fn load(name_or_path: &str) -> Result<Theme, ThemeError> {
+        match Theme::named(name_or_path) {
+            Some(theme) => Ok(theme),
+            None => Theme::from_file(name_or_path),
+        }
+    }
+
+    pub fn from_file(file_path: impl AsRef<Path>) -> Result<Theme, ThemeError> {
+        let file_path = file_path.as_ref();
+        let mut theme = Theme::parse(&std::fs::read_to_string(file_path)?)?;
+        if theme.name.is_empty() {
+            theme.name = file_path
+                .file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+        }
+        Ok(theme)
+    }
+
+    /// Parses a theme file. The name is left empty if the file has none.
+    pub fn parse(content: &str) -> Result<Theme, ThemeError> {
+        let file: ThemeFile = toml::from_str(content)?;
+        let base = file.base.as_deref().unwrap_or("classic");
+        let mut theme =
+            Theme::named(base).ok_or_else(|| ThemeError::UnknownTheme(base.to_string()))?;
+
+        theme.name = file.name.unwrap_or_default();
+        if let Some(wide) = file.wide {
+            theme.wide = wide;
+        }
+        if let Some(joined_walls) = file.joined_walls {
+            theme.joined_walls = joined_walls;
+        }
+        let tiles = [
+            (file.wall, &mut theme.wall),
+            (file.floor, &mut theme.floor),
+            (file.target, &mut theme.target),
+            (file.box_, &mut theme.box_),
+            (file.box_on_target, &mut theme.box_on_target),
+            (file.player, &mut theme.player),
+            (file.route, &mut theme.route),
+            (file.dead, &mut theme.dead),
+            (file.reachable, &mut theme.reachable),
+            (file.push_reachable, &mut theme.push_reachable),
+            (file.frozen, &mut theme.frozen),
+            (file.hint_box, &mut theme.hint_box),
+        ];
+        for (tile_file, tile) in tiles {
+            if let Some(tile_file) = tile_file {
+                tile_file.merge_into(tile)?;
+            }
+        }
+
+        Ok(theme)
+    }
+
+    pub fn tile(&self, tile: Tile) -> &TileStyle {
+        match tile {
+            Tile::Wall => &self.wall,
+            Tile::Floor => &self.floor,
+            Tile::Target => &self.target,
+            Tile::Box => &self.box_,
+            Tile::BoxOnTarget => &self.box_on_target,
+            Tile::Player => &self.player,
+            Tile::Route => &self.route,
+            Tile::Dead => &self.dead,
+            Tile::Reachable => &self.reachable,
+            Tile::PushReachable => &self.push_reachable,
+            Tile::Frozen => &self.frozen,
+            Tile::HintBox => &self.hint_box,
+        }
+    }
+
+    /// How many terminal columns a cell takes in normal zoom.
+    pub fn cell_width(&self) -> i32 {
+        if self.wide { 2 } else { 1 }
+    }
+
+    pub fn span(&self, tile: Tile) -> Span<'static> {
+        let style = self.tile(tile);
+        Span::styled(style.symbol.clone(), style.style())
+    }
+
+    /// The symbol padded to two columns, so cells come out roughly square.
+    /// Walls repeat their symbol so they stay solid.
+    pub fn wide_span(&self, tile: Tile) -> Span<'static> {
+        let style = self.tile(tile);
+        let symbol = if self.wide {
+            style.symbol.clone()
+        } else if tile == Tile::Wall {
+            style.symbol.repeat(2)
+        } else {
+            format!("{} ", style.symbol)
+        };
+        Span::styled(symbol, style.style())
+    }
+
+    /// A wall drawn as a line towards each of its neighbouring walls. In
+    /// wide zoom the line to the right carries on into the second column.
+    pub fn joined_wall_span(&self, joins: WallJoins, wide: bool) -> Span<'static> {
+        let mut symbol = String::from(joins.glyph());
+        if wide {
+            symbol.push(if joins.right { '─' } else { ' ' });
+        }
+        Span::styled(symbol, self.wall.style())
+    }
+
+    pub fn block(&self, tile: Tile) -> Color {
+        self.tile(tile).block
+    }
+
+    fn classic() -> Theme {
+        Theme {
+            name: String::from("classic"),
+            wide: false,
+            joined_walls: false,
+            wall: TileStyle::new("#", Some(Color::White), None, Color::Gray),
+            floor: TileStyle::new(" ", Some(Color::White), None, Color::Reset),
+            target: TileStyle::new(".", Some(Color::Red), None, Color::Red),
+            box_: TileStyle::new("$", Some(Color::Yellow), None, Color::Yellow),
+            box_on_target: TileStyle::new("*", None, Some(Color::Red), Color::Magenta),
+            player: TileStyle::new("P", Some(Color::Blue), None, Color::Blue).bold(),
+            route: TileStyle::new("O", Some(Color::Green), None, Color::Green),
+            dead: TileStyle::new("X", Some(Color::Red), None, Color::DarkGray),
+            reachable: TileStyle::new("-", Some(Color::Cyan), None, Color::Cyan),
+            push_reachable: TileStyle::new("+", Some(Color::Magenta), None, Color::LightMagenta),
+            frozen: TileStyle::new("$", Some(Color::White), Some(Color::Blue), Color::LightRed),
+            hint_box: TileStyle::new(
+                "$",
+                Some(Color::Black),
+                Some(Color::Green),
+                Color::LightGreen,
+            ),
+        }
+    }
+
+    fn unicode() -> Theme {
+        Theme {
+            name: String::from("unicode"),
+            wide: false,
+            joined_walls: true,
+            wall: TileStyle::new("█", Some(Color::Gray), None, Color::Gray),
+            floor: TileStyle::new(" ", None, None, Color::Reset),
+            target: TileStyle::new("·", Some(Color::Red), None, Color::Red),
+            box_: TileStyle::new("■", Some(Color::Yellow), None, Color::Yellow),
+            box_on_target: TileStyle::new("▣", Some(Color::Green), None, Color::Green),
+            player: TileStyle::new("●", Some(Color::Blue), None, Color::Blue).bold(),
+            route: TileStyle::new("∘", Some(Color::Green), None, Color::LightGreen),
+            dead: TileStyle::new("×", Some(Color::DarkGray), None, Color::DarkGray),
+            reachable: TileStyle::new("░", Some(Color::Cyan), None, Color::Cyan),
+            push_reachable: TileStyle::new("◇", Some(Color::Magenta), None, Color::LightMagenta),
+            frozen: TileStyle::new("■", Some(Color::Red), None, Color::LightRed).bold(),
+            hint_box: TileStyle::new(
+                "■",
+                Some(Color::Black),
+                Some(Color::Green),
+                Color::LightGreen,
+            ),
+        }
+    }
+
+    fn emoji() -> Theme {
+        Theme {
+            name: String::from("emoji"),
+            wide: true,
+            joined_walls: false,
+            wall: TileStyle::new("🧱", None, None, Color::Gray),
+            floor: TileStyle::new("  ", None, None, Color::Reset),
+            target: TileStyle::new("🎯", None, None, Color::Red),
+            box_: TileStyle::new("📦", None, None, Color::Yellow),
+            box_on_target: TileStyle::new("✅", None, None, Color::Green),
+            player: TileStyle::new("🙂", None, None, Color::Blue),
+            route: TileStyle::new("👣", None, None, Color::LightGreen),
+            dead: TileStyle::new("❌", None, None, Color::DarkGray),
+            reachable: TileStyle::new("░░", Some(Color::Cyan), None, Color::Cyan),
+            push_reachable: TileStyle::new("🔷", None, None, Color::LightMagenta),
+            frozen: TileStyle::new("🧊", None, None, Color::LightRed),
+            hint_box: TileStyle::new("📦", None, Some(Color::Green), Color::LightGreen),
+        }
+    }
+
+    fn high_contrast() -> Theme {
+        Theme {
+            name: String::from("high-contrast"),
+            wide: false,
+            joined_walls: false,
+            wall: TileStyle::new("#", Some(Color::Black), Some(Color::White), Color::White),
+            floor: TileStyle::new(" ", None, None, Color::Black),
+            target: TileStyle::new(".", Some(Color::LightYellow), None, Color::LightYellow).bold(),
+            box_: TileStyle::new("$", Some(Color::White), Some(Color::Blue), Color::Blue).bold(),
+            box_on_target: TileStyle::new(
+                "*",
+                Some(Color::Black),
+                Some(Color::LightGreen),
+                Color::LightGreen,
+            )
+            .bold(),
+            player: TileStyle::new(
+                "@",
+                Some(Color::Black),
+                Some(Color::LightYellow),
+                Color::LightMagenta,
+            )
+            .bold(),
+            route: TileStyle::new("o", Some(Color::White), None, Color::LightCyan).bold(),
+            dead: TileStyle::new("x", Some(Color::Gray), None, Color::DarkGray),
+            reachable: TileStyle::new("-", Some(Color::White), None, Color::Gray),
+            push_reachable: TileStyle::new("+", Some(Color::LightCyan), None, Color::LightCyan)
+                .bold(),
+            frozen: TileStyle::new("$", Some(Color::White), Some(Color::Red), Color::Red).bold(),
+            hint_box: TileStyle::new(
+                "$",
+                Some(Color::Black),
+                Some(Color::LightCyan),
+                Color::LightCyan,
+            )
+            .bold(),
+        }
+    }
+
+    /// Uses the Okabe-Ito palette, which stays distinguishable with the
+    /// common kinds of colour blindness, and never relies on colour alone.
+    fn colorblind() -> Theme {
+        let blue = Color::Rgb(0x00, 0x72, 0xb2);
+        let orange = Color::Rgb(0xe6, 0x9f, 0x00);
+        let sky_blue = Color::Rgb(0x56, 0xb4, 0xe9);
+        let green = Color::Rgb(0x00, 0x9e, 0x73);
+        let yellow = Color::Rgb(0xf0, 0xe4, 0x42);
+        let vermillion = Color::Rgb(0xd5, 0x5e, 0x00);
+        let purple = Color::Rgb(0xcc, 0x79, 0xa7);
+        let grey = Color::Rgb(0x99, 0x99, 0x99);
+
+        Theme {
+            name: String::from("colorblind"),
+            wide: false,
+            joined_walls: false,
+            wall: TileStyle::new("#", Some(Color::Gray), None, Color::Gray),
+            floor: TileStyle::new(" ", None, None, Color::Reset),
+            target: TileStyle::new("+", Some(sky_blue), None, sky_blue).bold(),
+            box_: TileStyle::new("$", Some(orange), None, orange).bold(),
+            box_on_target: TileStyle::new("*", Some(Color::Black), Some(green), green),
+            player: TileStyle::new("@", Some(blue), None, blue).bold(),
+            route: TileStyle::new("o", Some(yellow), None, yellow),
+            dead: TileStyle::new("x", Some(vermillion), None, vermillion),
+            reachable: TileStyle::new("-", Some(grey), None, grey),
+            push_reachable: TileStyle::new("~", Some(purple), None, purple).bold(),
+            frozen: TileStyle::new("$", Some(Color::Black), Some(vermillion), vermillion),
+            hint_box: TileStyle::new("$", Some(Color::Black), Some(yellow), yellow).bold(),
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::classic()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_merges_a_file_onto_its_base() {
+        let theme = Theme::parse(
+            r##"
+            base = "unicode"
+            name = "mine"
+            joined_walls = false
+
+            [player]
+            symbol = "@"
+            fg = "#ff8800"
+
+            [box]
+            bg = "12"
+            bold = true
+            "##,
+        )
+        .unwrap();
+        let unicode = Theme::named("unicode").unwrap();
+
+        assert_eq!(theme.name, "mine");
+        assert!(!theme.joined_walls);
+        assert_eq!(theme.player.symbol, "@");
+        assert_eq!(theme.player.fg, Some(Color::Rgb(0xff, 0x88, 0x00)));
+        assert!(theme.player.bold);
+        assert_eq!(theme.box_.symbol, unicode.box_.symbol);
+        assert_eq!(theme.box_.bg, Some(Color::Indexed(12)));
+        assert!(theme.box_.bold);
+        assert_eq!(theme.wall, unicode.wall);
+    }
+
+    #[test]
+    fn parse_starts_from_classic_without_a_base() {
+        let theme = Theme::parse("[target]\nsymbol = \"o\"").unwrap();
+        let classic = Theme::default();
+
+        assert!(theme.name.is_empty());
+        assert_eq!(theme.target.symbol, "o");
+        assert_eq!(theme.wall, classic.wall);
+    }
+
+    #[test]
+    fn parse_rejects_bad_themes() {
+        assert!(matches!(
+            Theme::parse("base = \"neon\""),
+            Err(ThemeError::UnknownTheme(name)) if name == "neon"
+        ));
+        assert!(matches!(
+            Theme::parse("[wall]\nfg = \"not a colour\""),
+            Err(ThemeError::InvalidColor(color)) if color == "not a colour"
+        ));
+        assert!(matches!(
+            Theme::parse("[lava]\nsymbol = \"~\""),
+            Err(ThemeError::Parse(_))
+        ));
+    }
+}
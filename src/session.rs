@@ -0,0 +1,154 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::history::ParseLurdError;
+
+/// A game in progress, saved when the player quits so it can be continued
+/// later.
+///
+/// Only the moves are needed to restore the position. The board is kept as
+/// well to check that replaying them on the level gives the same position.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    /// The level file or directory the game was started from.
+    pub source: PathBuf,
+    /// 1-based number of the level within `source`.
+    pub level: usize,
+    /// The `progress::level_key` of the level, to find it again if the
+    /// collection changed.
+    pub level_key: String,
+    /// The position when the game was saved, as XSB.
+    pub board: String,
+    /// Every move, the redo branch included, in LURD notation.
+    pub moves: String,
+    /// How many of `moves` were made. The rest can be redone.
+    pub cursor: usize,
+    /// Time spent on the level, in milliseconds.
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug)]
+pub enum SessionError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+    Lurd(ParseLurdError),
+    /// The saved level is no longer part of the collection.
+    LevelMissing(PathBuf),
+    /// Replaying the saved moves does not give the saved position.
+    Mismatch,
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::Io(err) => write!(f, "failed to access saved game: {err}"),
+            SessionError::Parse(err) => write!(f, "invalid saved game: {err}"),
+            SessionError::Lurd(err) => write!(f, "invalid moves in saved game: {err}"),
+            SessionError::LevelMissing(source) => {
+                write!(f, "the saved level is no longer in {}", source.display())
+            }
+            SessionError::Mismatch => {
+                write!(f, "the saved moves do not lead to the saved position")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SessionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SessionError::Io(err) => Some(err),
+            SessionError::Parse(err) => Some(err),
+            SessionError::Lurd(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for SessionError {
+    fn from(err: std::io::Error) -> Self {
+        SessionError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for SessionError {
+    fn from(err: serde_json::Error) -> Self {
+        SessionError::Parse(err)
+    }
+}
+
+impl From<ParseLurdError> for SessionError {
+    fn from(err: ParseLurdError) -> Self {
+        SessionError::Lurd(err)
+    }
+}
+
+impl Session {
+    /// Where the game is saved, e.g. `~/.local/share/sokoban-rs/session.json`
+    /// on Linux.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("sokoban-rs").join("session.json"))
+    }
+
+    /// Reads a saved game, or `None` if there is no file.
+    pub fn from_file(file_path: impl AsRef<Path>) -> Result<Option<Self>, SessionError> {
+        match std::fs::read_to_string(file_path) {
+            Ok(content) => Ok(Some(serde_json::from_str(&content)?)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes the game to `file_path`, creating its directory if needed.
+    pub fn save(&self, file_path: impl AsRef<Path>) -> Result<(), SessionError> {
+        let file_path = file_path.as_ref();
+        if let Some(dir) = file_path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(file_path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Deletes a saved game. A missing file is not an error.
+    pub fn remove(file_path: impl AsRef<Path>) -> Result<(), SessionError> {
+        match std::fs::remove_file(file_path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_millis(self.elapsed_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_saved_session_reads_back_and_can_be_removed() {
+        let path =
+            std::env::temp_dir().join(format!("sokoban-rs-session-{}.json", std::process::id()));
+        let session = Session {
+            source: PathBuf::from("levels/mission1.txt"),
+            level: 1,
+            level_key: String::from("0123456789abcdef"),
+            board: String::from("#####\n#@$.#\n#####\n"),
+            moves: String::from("rl"),
+            cursor: 1,
+            elapsed_ms: 1_500,
+        };
+
+        session.save(&path).unwrap();
+        assert_eq!(Session::from_file(&path).unwrap(), Some(session));
+        Session::remove(&path).unwrap();
+        assert_eq!(Session::from_file(&path).unwrap(), None);
+        Session::remove(&path).unwrap();
+    }
+}